@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tuitable::{Cell, TimeSource};
+
+/// bpf 里 `bpf_mono_now()` 用的时钟
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MonoClock {
+    /// `bpf_ktime_get_ns()` 换算成秒
+    Ktime,
+    /// `jiffies >> 8`，开启 `ENABLE_JIFFIES` 时使用
+    Jiffies { hz: u64 },
+}
+
+const BPF_MONO_SCALER: u32 = 8;
+
+impl MonoClock {
+    /// 从 agent 生成的 node_config.h 判断，读不到时按 ktime 处理
+    pub fn detect(node_config: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(node_config) else {
+            return MonoClock::Ktime;
+        };
+        let define = |name: &str| {
+            content.lines().find_map(|line| {
+                let mut words = line.split_whitespace();
+                match (words.next(), words.next(), words.next()) {
+                    (Some("#define"), Some(n), value) if n == name => Some(value.unwrap_or("")),
+                    _ => None,
+                }
+            })
+        };
+        match (define("ENABLE_JIFFIES"), define("KERNEL_HZ")) {
+            (Some(_), Some(hz)) => match hz.parse() {
+                Ok(hz) => MonoClock::Jiffies { hz },
+                Err(_) => MonoClock::Ktime,
+            },
+            _ => MonoClock::Ktime,
+        }
+    }
+}
+
+/// 读取时的各个时钟，用于把 map 里的时间换算成 unix 时间
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    mono: MonoClock,
+    /// `bpf_ktime_get_ns()`
+    ktime: u64,
+    jiffies: u64,
+    /// unix 纳秒
+    wall: i64,
+}
+
+impl Clock {
+    /// 当前的 ktime 和 jiffies 都从 /proc/timer_list 读取
+    pub fn now(mono: MonoClock) -> Result<Self> {
+        let timer_list = std::fs::read_to_string("/proc/timer_list")
+            .context("failed to read /proc/timer_list")?;
+        let field = |prefix: &str| {
+            timer_list
+                .lines()
+                .find_map(|line| line.strip_prefix(prefix))
+                .and_then(|value| value.split_whitespace().next())
+                .and_then(|value| value.parse::<u64>().ok())
+                .with_context(|| format!("no {:?} in /proc/timer_list", prefix))
+        };
+        Ok(Self {
+            mono,
+            ktime: field("now at ")?,
+            jiffies: field("jiffies: ")?,
+            wall: SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as i64,
+        })
+    }
+
+    /// 换算成 `Cell::Time`，超出范围时保留原始的 `Cell::Timestamp`
+    pub fn resolve(&self, value: u64, source: TimeSource, iso: bool) -> Cell {
+        let delta = match source {
+            TimeSource::Mono => self.mono_delta(value),
+            TimeSource::Ktime => i64::try_from(value)
+                .ok()
+                .and_then(|value| value.checked_sub(self.ktime as i64)),
+            TimeSource::Unix => i64::try_from(value)
+                .ok()
+                .and_then(|value| value.checked_sub(self.wall)),
+        };
+        match delta.and_then(|delta| self.wall.checked_add(delta)) {
+            Some(at) => Cell::Time {
+                value,
+                at,
+                now: self.wall,
+                iso,
+            },
+            None => Cell::Timestamp { value, source },
+        }
+    }
+
+    /// `bpf_mono_now()` 的值距现在多少纳秒。ct 里只保存了低 32 位，所以只比较低 32 位
+    fn mono_delta(&self, time: u64) -> Option<i64> {
+        let now = match self.mono {
+            MonoClock::Ktime => self.ktime / 1_000_000_000,
+            MonoClock::Jiffies { .. } => self.jiffies >> BPF_MONO_SCALER,
+        };
+        let delta = (time as u32).wrapping_sub(now as u32) as i32 as i64;
+        match self.mono {
+            MonoClock::Ktime => delta.checked_mul(1_000_000_000),
+            MonoClock::Jiffies { hz } => (delta << BPF_MONO_SCALER)
+                .checked_mul(1_000_000_000)?
+                .checked_div(hz as i64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCK: Clock = Clock {
+        mono: MonoClock::Jiffies { hz: 250 },
+        ktime: 1_000_000_000_000,
+        jiffies: 1_000_000,
+        wall: 1_700_000_000_000_000_000,
+    };
+
+    #[test]
+    fn resolve_unix_nanoseconds() {
+        let value = CLOCK.wall as u64 + 30_000_000_000;
+        assert!(matches!(
+            CLOCK.resolve(value, TimeSource::Unix, false),
+            Cell::Time { value: raw, at, now, .. } if raw == value && at - now == 30_000_000_000
+        ));
+    }
+
+    #[test]
+    fn resolve_out_of_range() {
+        for source in [TimeSource::Unix, TimeSource::Ktime] {
+            assert!(matches!(
+                CLOCK.resolve(u64::MAX, source, false),
+                Cell::Timestamp {
+                    value: u64::MAX,
+                    ..
+                }
+            ));
+        }
+        // jiffies 换算成纳秒时不能溢出
+        assert!(matches!(
+            CLOCK.resolve(u64::MAX, TimeSource::Mono, false),
+            Cell::Time { .. }
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tuitable::NumberFormat;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextMap,
+    PreviousMap,
+    NextRow,
+    PreviousRow,
+    Open,
+    List,
+    Delete,
+    Sort,
+    ReverseSort,
+    Detail,
+    ToggleTime,
+    CycleNumbers,
+}
+
+impl Action {
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::NextMap => &["j"],
+            Action::PreviousMap => &["k"],
+            Action::NextRow => &["ctrl-j"],
+            Action::PreviousRow => &["ctrl-k"],
+            Action::Open => &["l"],
+            Action::List => &["h"],
+            Action::Delete => &["d"],
+            Action::Sort => &["s"],
+            Action::ReverseSort => &["S"],
+            Action::Detail => &["enter"],
+            Action::ToggleTime => &["t"],
+            Action::CycleNumbers => &["u"],
+        }
+    }
+
+    const ALL: [Action; 13] = [
+        Action::Quit,
+        Action::NextMap,
+        Action::PreviousMap,
+        Action::NextRow,
+        Action::PreviousRow,
+        Action::Open,
+        Action::List,
+        Action::Delete,
+        Action::Sort,
+        Action::ReverseSort,
+        Action::Detail,
+        Action::ToggleTime,
+        Action::CycleNumbers,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PercpuMode {
+    /// 每个 CPU 一行
+    #[default]
+    Split,
+    /// 按 key 合并所有 CPU 的计数
+    Sum,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MapConfig {
+    pub hidden: Vec<String>,
+    pub percpu: Option<PercpuMode>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 只读取这个 cilium-agent 进程的 map
+    pub pid: Option<u32>,
+    pub bpffs: PathBuf,
+    pub map_filter: String,
+    /// 自动刷新间隔，单位秒，0 表示不刷新
+    pub refresh_interval: u64,
+    pub percpu: PercpuMode,
+    /// 允许删除 map 中的条目
+    pub write: bool,
+    /// cilium-agent 容器内 node_config.h 的路径，用于判断 bpf 用的时钟
+    pub node_config: PathBuf,
+    /// 只显示这么多秒内过期（包括已经过期）的 ct 条目
+    pub expires_within: Option<u64>,
+    /// 时间显示成 ISO 时间，否则显示相对现在的时间
+    pub iso_time: bool,
+    /// 字节数、包数等计数的显示方式，没有设置时命令行输出原始的数值，交互界面带单位显示
+    pub numbers: Option<NumberFormat>,
+    keys: HashMap<Action, Keys>,
+    pub maps: HashMap<String, MapConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pid: None,
+            bpffs: PathBuf::from("/sys/fs/bpf/tc/globals"),
+            map_filter: "^(policy\
+                         |tunnel map\
+                         |ct4 global\
+                         |metrics\
+                         |ct any4 global\
+                         |ct6 global\
+                         |ct any6 global\
+                         |lb4 reverse nat\
+                         |lb4 reverse sk\
+                         |lb4 services v2\
+                         |lb4 backends v3\
+                         |lb6 reverse nat\
+                         |lb6 reverse sk\
+                         |lb6 services v2\
+                         |lb6 backends v3\
+                         |snat v4 external\
+                         |snat v6 external\
+                         |lxc\
+                         |egress gw policy v4\
+                         |srv6 (vrf|policy|state) v[46]\
+                         |vtep map\
+                         |throttle\
+                         |auth map\
+                         |encrypt state\
+                         |lb[46] source range\
+                         |ipcache)"
+                .to_string(),
+            refresh_interval: 0,
+            percpu: PercpuMode::default(),
+            write: false,
+            node_config: PathBuf::from("/var/run/cilium/state/globals/node_config.h"),
+            expires_within: None,
+            iso_time: false,
+            numbers: None,
+            keys: HashMap::new(),
+            maps: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// 读取 `path`，未指定时读取 `$XDG_CONFIG_HOME/cilium_map_viewer/config.toml`，
+    /// 该文件不存在时使用默认配置
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let config: Self = toml::from_str(&content)
+            .with_context(|| format!("invalid config {}", path.display()))?;
+        config.keymap()?;
+        Ok(config)
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("cilium_map_viewer").join("config.toml"))
+    }
+
+    pub fn map(&self, name: &str) -> Option<&MapConfig> {
+        self.maps.get(name)
+    }
+
+    pub fn percpu(&self, name: &str) -> PercpuMode {
+        self.map(name)
+            .and_then(|map| map.percpu)
+            .unwrap_or(self.percpu)
+    }
+
+    /// 配置中出现的 action 会替换掉它的默认按键，
+    /// 配置的按键和其它 action 的默认按键相同时，那个默认按键不再生效
+    pub fn keymap(&self) -> Result<KeyMap> {
+        let mut bindings = HashMap::new();
+        for (&action, keys) in &self.keys {
+            let keys = match keys {
+                Keys::One(key) => std::slice::from_ref(key),
+                Keys::Many(keys) => &keys[..],
+            };
+            for key in keys {
+                if let Some(other) = bindings.insert(parse_key(key)?, action) {
+                    if other != action {
+                        bail!(
+                            "key {:?} is bound to both {:?} and {:?}",
+                            key,
+                            other,
+                            action
+                        );
+                    }
+                }
+            }
+        }
+        for action in Action::ALL {
+            if self.keys.contains_key(&action) {
+                continue;
+            }
+            for key in action.default_keys() {
+                bindings.entry(parse_key(key)?).or_insert(action);
+            }
+        }
+        Ok(KeyMap { bindings })
+    }
+}
+
+pub struct KeyMap {
+    bindings: HashMap<(KeyCode, KeyModifiers), Action>,
+}
+
+impl KeyMap {
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        let mut modifiers = key.modifiers;
+        // 大写字母自带 SHIFT，配置里直接写 "G" 即可
+        if let KeyCode::Char(c) = key.code {
+            if c.is_ascii_uppercase() {
+                modifiers.remove(KeyModifiers::SHIFT);
+            }
+        }
+        self.bindings.get(&(key.code, modifiers)).copied()
+    }
+}
+
+/// 解析 "q"、"ctrl-j"、"down"、"shift-tab"、"f5" 这样的按键描述
+fn parse_key(s: &str) -> Result<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = s;
+    while let Some((modifier, tail)) = rest.split_once('-') {
+        if tail.is_empty() {
+            break;
+        }
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => bail!("unknown modifier {:?} in {:?}", modifier, s),
+        };
+        rest = tail;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) && c.is_ascii_lowercase() => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            "minus" => KeyCode::Char('-'),
+            f if f.starts_with('f') => {
+                KeyCode::F(f[1..].parse().map_err(|_| anyhow!("unknown key {:?}", s))?)
+            }
+            _ => bail!("unknown key {:?}", s),
+        },
+    };
+    Ok((code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(toml: &str) -> Result<KeyMap> {
+        toml::from_str::<Config>(toml).unwrap().keymap()
+    }
+
+    fn action(keymap: &KeyMap, key: &str) -> Option<Action> {
+        let (code, modifiers) = parse_key(key).unwrap();
+        keymap.action(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn parse_keys() {
+        let upper = (KeyCode::Char('S'), KeyModifiers::NONE);
+        assert_eq!(parse_key("S").unwrap(), upper);
+        assert_eq!(parse_key("shift-s").unwrap(), upper);
+        assert_eq!(
+            parse_key("ctrl-j").unwrap(),
+            (KeyCode::Char('j'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse_key("f5").unwrap(),
+            (KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_key("space").unwrap(),
+            (KeyCode::Char(' '), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_key("-").unwrap(),
+            (KeyCode::Char('-'), KeyModifiers::NONE)
+        );
+        for bad in ["nope", "foo-x", "fx", ""] {
+            assert!(parse_key(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn configured_key_overrides_default() {
+        let keymap = keymap("[keys]\nopen = [\"l\", \"enter\"]\n").unwrap();
+        assert_eq!(action(&keymap, "enter"), Some(Action::Open));
+        assert_eq!(action(&keymap, "l"), Some(Action::Open));
+        // 其它 action 的默认按键不受影响
+        assert_eq!(action(&keymap, "S"), Some(Action::ReverseSort));
+    }
+
+    #[test]
+    fn configured_action_drops_its_defaults() {
+        let keymap = keymap("[keys]\ndetail = \"space\"\n").unwrap();
+        assert_eq!(action(&keymap, "space"), Some(Action::Detail));
+        assert_eq!(action(&keymap, "enter"), None);
+    }
+
+    #[test]
+    fn duplicate_binding() {
+        assert!(keymap("[keys]\nopen = \"x\"\ndelete = \"x\"\n").is_err());
+        assert!(keymap("[keys]\nopen = [\"x\", \"x\"]\n").is_ok());
+    }
+}
@@ -1,4 +1,6 @@
+use std::fmt;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use tuitable::{Cell, ToCell};
 
 #[derive(Default)]
 pub struct Ip {
@@ -27,15 +29,27 @@ impl IpFamily {
     pub fn is_ipv4(&self) -> bool {
         self.family == ENDPOINT_KEY_IPV4
     }
-}
 
-impl ToString for IpFamily {
-    fn to_string(&self) -> String {
-        match self.family {
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.family {
             ENDPOINT_KEY_IPV4 => "ipv4",
             ENDPOINT_KEY_IPV6 => "ipv6",
-            _ => "",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_cell().fmt(f)
+    }
+}
+
+impl ToCell for IpFamily {
+    fn to_cell(&self) -> Cell {
+        Cell::Enum {
+            value: self.family as u64,
+            name: self.name(),
         }
-        .to_string()
     }
 }
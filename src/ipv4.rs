@@ -1,13 +1,26 @@
+use std::fmt;
 use std::net::Ipv4Addr;
-use std::string::ToString;
+use tuitable::{Cell, ToCell};
 
 #[derive(Default)]
 pub struct Ipv4 {
     octets: [u8; 4],
 }
 
-impl ToString for Ipv4 {
-    fn to_string(&self) -> String {
-        Ipv4Addr::from(self.octets).to_string()
+impl Ipv4 {
+    pub fn addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.octets)
+    }
+}
+
+impl fmt::Display for Ipv4 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.addr().fmt(f)
+    }
+}
+
+impl ToCell for Ipv4 {
+    fn to_cell(&self) -> Cell {
+        self.addr().to_cell()
     }
 }
@@ -1,13 +1,26 @@
+use std::fmt;
 use std::net::Ipv6Addr;
-use std::string::ToString;
+use tuitable::{Cell, ToCell};
 
 #[derive(Default)]
 pub struct Ipv6 {
     octets: [u8; 16],
 }
 
-impl ToString for Ipv6 {
-    fn to_string(&self) -> String {
-        Ipv6Addr::from(self.octets).to_string()
+impl Ipv6 {
+    pub fn addr(&self) -> Ipv6Addr {
+        Ipv6Addr::from(self.octets)
+    }
+}
+
+impl fmt::Display for Ipv6 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.addr().fmt(f)
+    }
+}
+
+impl ToCell for Ipv6 {
+    fn to_cell(&self) -> Cell {
+        self.addr().to_cell()
     }
 }
@@ -8,7 +8,13 @@ mod mac;
 pub use mac::Mac;
 
 mod l4proto;
-pub use l4proto::L4Proto;
+pub use l4proto::{L4Proto, IPPROTO_ICMP, IPPROTO_ICMPV6};
+
+mod clock;
+pub use clock::{Clock, MonoClock};
+
+mod config;
+pub use config::{Action, Config, KeyMap, MapConfig, PercpuMode};
 
 mod table;
 pub use table::{run_app, App};
@@ -16,7 +22,10 @@ mod types;
 pub use types::*;
 
 mod map;
-pub use map::dump;
+pub use map::{decode, dump, find, list, MapTable, SEPARATOR};
+
+mod output;
+pub use output::{write, Format};
 
 mod port;
 pub use port::Port;
@@ -1,13 +1,20 @@
-use std::string::ToString;
+use std::fmt;
+use tuitable::{Cell, ToCell};
 
 #[derive(Default)]
 pub struct Mac {
     octets: [u8; 6],
 }
 
-impl ToString for Mac {
-    fn to_string(&self) -> String {
+impl fmt::Display for Mac {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let hex: Vec<_> = self.octets.iter().map(|n| format!("{:02x}", n)).collect();
-        hex.join(":")
+        f.write_str(&hex.join(":"))
+    }
+}
+
+impl ToCell for Mac {
+    fn to_cell(&self) -> Cell {
+        Cell::Mac(self.octets)
     }
 }
@@ -1,11 +1,17 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use cilium_map_viewer::*;
+use clap::{Args, Parser, Subcommand};
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use libbpf_rs::MapHandle;
 use ratatui::prelude::*;
-use std::io::Stdout;
+use serde_json::{json, Value};
+use std::io::{Stdout, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tuitable::{Cell, NumberFormat};
 
 struct UI {
     terminal: Terminal<CrosstermBackend<Stdout>>,
@@ -19,12 +25,11 @@ impl UI {
         Ok(Self { terminal })
     }
 
-    fn run(&mut self) -> Result<()> {
+    fn run(&mut self, app: App) -> Result<()> {
         enable_raw_mode()?;
         execute!(std::io::stdout(), EnterAlternateScreen)?;
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
-        let app = App::default();
         run_app(&mut self.terminal, app)
     }
 }
@@ -43,7 +48,218 @@ impl Drop for UI {
     }
 }
 
+/// 查看 cilium 的 bpf map
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// 配置文件，默认 $XDG_CONFIG_HOME/cilium_map_viewer/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// 只读取这个 cilium-agent 进程的 map
+    #[arg(long, global = true)]
+    pid: Option<u32>,
+    /// cilium-agent 容器内 bpffs 的路径
+    #[arg(long, global = true)]
+    bpffs: Option<PathBuf>,
+    /// 刷新间隔（秒）
+    #[arg(short, long, global = true)]
+    interval: Option<u64>,
+    /// 允许删除 map 中的条目
+    #[arg(long, global = true, overrides_with = "read_only")]
+    write: bool,
+    /// 只读模式（默认）
+    #[arg(long, global = true, overrides_with = "write")]
+    read_only: bool,
+    /// 只显示这么多秒内过期的 ct 条目
+    #[arg(long, global = true, value_name = "SECS")]
+    expires_within: Option<u64>,
+    /// 时间显示成 ISO 时间（UTC），默认显示相对现在的时间
+    #[arg(long, global = true)]
+    iso_time: bool,
+    /// 计数的显示方式：raw、grouped（千位分隔符）或 units（KiB、Gbit/s 等单位），默认命令行输出 raw，交互界面是 units
+    #[arg(long, global = true)]
+    numbers: Option<NumberFormat>,
+    #[command(flatten)]
+    tui: TuiArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Args)]
+struct TuiArgs {
+    /// 启动后直接打开这个 map
+    #[arg(short, long)]
+    map: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 交互界面（默认）
+    Tui(TuiArgs),
+    /// 列出 map
+    List {
+        #[arg(short, long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// 输出一个 map 的内容
+    Dump {
+        map: String,
+        #[arg(short, long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// 输出所有支持的 map，指定目录时每个 map 写一个文件
+    Snapshot {
+        dir: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// 按刷新间隔反复输出一个 map 的内容
+    Watch {
+        map: String,
+        #[arg(short, long, value_enum, default_value_t)]
+        format: Format,
+        /// 输出多少次后退出
+        #[arg(short = 'n', long)]
+        count: Option<usize>,
+    },
+}
+
+impl Cli {
+    fn config(&self) -> Result<Config> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if self.pid.is_some() {
+            config.pid = self.pid;
+        }
+        if let Some(bpffs) = &self.bpffs {
+            config.bpffs = bpffs.clone();
+        }
+        if let Some(interval) = self.interval {
+            config.refresh_interval = interval;
+        }
+        if self.write {
+            config.write = true;
+        }
+        if self.read_only {
+            config.write = false;
+        }
+        if self.expires_within.is_some() {
+            config.expires_within = self.expires_within;
+        }
+        if self.iso_time {
+            config.iso_time = true;
+        }
+        if self.numbers.is_some() {
+            config.numbers = self.numbers;
+        }
+        Ok(config)
+    }
+}
+
+fn dump_map(config: &Config, name: &str) -> Result<(String, MapTable)> {
+    let (name, path) = find(config, name)?;
+    let map = MapHandle::from_pinned_path(&path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let table =
+        decode(&name, &map, config, None)?.with_context(|| format!("{} is not supported", name))?;
+    Ok((name, table))
+}
+
 fn main() -> Result<()> {
-    let mut ui = UI::new()?;
-    ui.run()
+    let cli = Cli::parse();
+    if cli.tui.map.is_some() && !matches!(cli.command, None | Some(Command::Tui(_))) {
+        bail!("--map only applies to the tui");
+    }
+    let config = cli.config()?;
+    let mut stdout = std::io::stdout();
+    match cli.command {
+        None | Some(Command::Tui(_)) => {
+            let map = match cli.command {
+                Some(Command::Tui(args)) => args.map.or(cli.tui.map),
+                _ => cli.tui.map,
+            };
+            let app = App::new(config)?.with_map(map);
+            let mut ui = UI::new()?;
+            ui.run(app)?;
+        }
+        Some(Command::List { format }) => {
+            let maps = list(&config)?;
+            let table = MapTable {
+                header: vec!["name".to_string(), "path".to_string()],
+                rows: maps
+                    .iter()
+                    .map(|(name, path)| {
+                        vec![
+                            Cell::Text(name.clone()),
+                            Cell::Text(path.display().to_string()),
+                        ]
+                    })
+                    .collect(),
+                keys: vec![],
+            };
+            write(&mut stdout, &table, format)?;
+        }
+        Some(Command::Dump { map, format }) => {
+            let (_, table) = dump_map(&config, &map)?;
+            write(&mut stdout, &table, format)?;
+        }
+        Some(Command::Snapshot { dir, format }) => {
+            if let Some(dir) = &dir {
+                std::fs::create_dir_all(dir)?;
+            }
+            let extension = match format {
+                Format::Table => "txt",
+                Format::Csv => "csv",
+                Format::Json => "json",
+            };
+            // json 输出到 stdout 时合并成一个 `{"map name": [..]}`
+            let mut maps = serde_json::Map::new();
+            for (name, path) in list(&config)? {
+                let map = MapHandle::from_pinned_path(&path)?;
+                let Some(table) = decode(&name, &map, &config, None)? else {
+                    continue;
+                };
+                match &dir {
+                    Some(dir) => {
+                        let file = dir.join(format!("{}.{}", name.replace(' ', "_"), extension));
+                        write(&mut std::fs::File::create(file)?, &table, format)?;
+                    }
+                    None if format == Format::Json => {
+                        maps.insert(name, Value::from(table.entries()));
+                    }
+                    None => {
+                        writeln!(stdout, "# {}", name)?;
+                        write(&mut stdout, &table, format)?;
+                        writeln!(stdout)?;
+                    }
+                }
+            }
+            if dir.is_none() && format == Format::Json {
+                serde_json::to_writer_pretty(&mut stdout, &maps)?;
+                writeln!(stdout)?;
+            }
+        }
+        Some(Command::Watch { map, format, count }) => {
+            let interval = Duration::from_secs(config.refresh_interval.max(1));
+            for i in 0..count.unwrap_or(usize::MAX) {
+                if i > 0 {
+                    std::thread::sleep(interval);
+                    if format != Format::Json {
+                        writeln!(stdout)?;
+                    }
+                }
+                let (name, table) = dump_map(&config, &map)?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                if format == Format::Json {
+                    // 每次输出一行，方便 jq 逐行处理
+                    let line = json!({ "map": name, "time": now, "entries": table.entries() });
+                    writeln!(stdout, "{}", line)?;
+                } else {
+                    writeln!(stdout, "# {} {}", name, now)?;
+                    write(&mut stdout, &table, format)?;
+                }
+                stdout.flush()?;
+            }
+        }
+    }
+    Ok(())
 }
@@ -1,50 +1,567 @@
-use anyhow::{Result};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use libbpf_rs::MapFlags;
-use libbpf_rs::{MapHandle};
+use libbpf_rs::MapHandle;
+use regex::Regex;
+use sysinfo::{PidExt, ProcessExt, ProcessRefreshKind, RefreshKind, System, SystemExt};
 
 use plain::Plain;
+use std::collections::HashMap;
 use std::mem::size_of;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
+use crate::*;
 
-use tuitable::TuiTable;
+use serde_json::{Map, Value};
+use tuitable::{Cell, NumberFormat, TuiTable};
 
-pub fn dump<K, V>(map: &MapHandle, percpu: bool) -> Result<(Vec<Vec<String>>, Vec<&'static str>)>
+/// 分隔 key 和 value 的列
+pub const SEPARATOR: &str = "│";
+
+#[derive(Default)]
+pub struct MapTable {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+    /// 每一行对应的原始 key，用于删除
+    pub keys: Vec<Vec<u8>>,
+}
+
+impl MapTable {
+    pub fn hide(&mut self, hidden: &[String]) {
+        let visible: Vec<_> = self
+            .header
+            .iter()
+            .map(|h| !hidden.iter().any(|c| c == h))
+            .collect();
+        self.header.retain(|h| !hidden.iter().any(|c| c == h));
+        for row in self.rows.iter_mut() {
+            let mut visible = visible.iter();
+            row.retain(|_| *visible.next().unwrap());
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&[Cell]) -> bool) {
+        let rows: Vec<_> = std::mem::take(&mut self.rows)
+            .into_iter()
+            .zip(std::mem::take(&mut self.keys))
+            .filter(|(row, _)| f(row))
+            .collect();
+        (self.rows, self.keys) = rows.into_iter().unzip();
+    }
+
+    /// 名字是 `name` 的第一列
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.header.iter().position(|h| h == name)
+    }
+
+    /// 在 `after` 列后面加一列，用 `f` 根据 `after` 列的值计算，没有 `after` 列时什么都不做
+    pub fn add_column(&mut self, after: &str, name: &str, mut f: impl FnMut(&Cell) -> Cell) {
+        let Some(i) = self.column(after) else {
+            return;
+        };
+        self.header.insert(i + 1, name.to_string());
+        for row in self.rows.iter_mut() {
+            let cell = f(&row[i]);
+            row.insert(i + 1, cell);
+        }
+    }
+
+    /// 只保留前 `len` 行
+    pub fn truncate(&mut self, len: usize) {
+        self.rows.truncate(len);
+        self.keys.truncate(len);
+    }
+
+    /// `expiration` 列的时间已经过去
+    pub fn is_expired(&self, row: usize) -> bool {
+        let Some(i) = self.column("expiration") else {
+            return false;
+        };
+        matches!(self.rows[row][i], Cell::Time { at, now, .. } if at <= now)
+    }
+
+    pub fn set_number_format(&mut self, number_format: NumberFormat) {
+        for cell in self.rows.iter_mut().flatten() {
+            if let Cell::Quantity { format, .. } = cell {
+                *format = number_format;
+            }
+        }
+    }
+
+    /// 切换时间的显示方式
+    pub fn set_iso_time(&mut self, iso_time: bool) {
+        for cell in self.rows.iter_mut().flatten() {
+            if let Cell::Time { iso, .. } = cell {
+                *iso = iso_time;
+            }
+        }
+    }
+
+    pub fn sort(&mut self, column: usize, reverse: bool) {
+        let mut rows: Vec<_> = std::mem::take(&mut self.rows)
+            .into_iter()
+            .zip(std::mem::take(&mut self.keys))
+            .collect();
+        rows.sort_by(|(a, _), (b, _)| {
+            let ordering = a[column].compare(&b[column]);
+            if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        (self.rows, self.keys) = rows.into_iter().unzip();
+    }
+
+    /// 每一行转成 `{"cpu": .., "key": {..}, "value": {..}}`，没有分隔列时直接是字段的 object
+    pub fn entries(&self) -> Vec<Value> {
+        let separator = self.column(SEPARATOR);
+        self.rows
+            .iter()
+            .map(|row| {
+                let fields = |range: std::ops::Range<usize>| {
+                    Value::Object(
+                        range
+                            .filter(|&i| self.header[i] != "cpu")
+                            .map(|i| (self.header[i].clone(), row[i].to_json()))
+                            .collect(),
+                    )
+                };
+                let Some(separator) = separator else {
+                    return fields(0..row.len());
+                };
+                let mut entry = Map::new();
+                if self.header.first().is_some_and(|h| h == "cpu") {
+                    entry.insert("cpu".to_string(), row[0].to_json());
+                }
+                entry.insert("key".to_string(), fields(0..separator));
+                entry.insert("value".to_string(), fields(separator + 1..row.len()));
+                Value::Object(entry)
+            })
+            .collect()
+    }
+}
+
+/// 读取 map，最多读取 `limit` 个 key，None 时读取所有条目
+pub fn dump<K, V>(
+    map: &MapHandle,
+    percpu: Option<PercpuMode>,
+    limit: Option<usize>,
+) -> Result<MapTable>
 where
     K: TuiTable + Default + Plain,
     V: TuiTable + Default + Plain,
 {
-    let mut header = vec![];
-    if percpu {
-        header.push("cpu");
-    }
-    header.extend(K::header());
-    header.push("│");
-    header.extend(V::header());
-    let mut rows = vec![];
-    for mut key in map.keys().take(100) {
+    let split = percpu == Some(PercpuMode::Split);
+    let mut table = MapTable::default();
+    if split {
+        table.header.push("cpu".to_string());
+    }
+    table.header.extend(K::header());
+    table.header.push(SEPARATOR.to_string());
+    table.header.extend(V::header());
+    for mut key in map.keys().take(limit.unwrap_or(usize::MAX)) {
         let mut k = K::default();
+        let raw_key = key.clone();
         key.extend(vec![0; size_of::<K>().saturating_sub(key.len())]);
         k.copy_from_bytes(&key).unwrap();
-        let mut values = if percpu {
-            map.lookup_percpu(&key, MapFlags::empty())?.unwrap()
+        let values = if percpu.is_some() {
+            map.lookup_percpu(&key, MapFlags::empty())?
         } else {
-            vec![map
-                .lookup(&key[..map.key_size() as usize], MapFlags::empty())?
-                .unwrap()]
+            map.lookup(&key[..map.key_size() as usize], MapFlags::empty())?
+                .map(|value| vec![value])
         };
+        // 遍历 key 之后条目可能已经被删除（比如 ct gc）
+        let Some(mut values) = values else {
+            continue;
+        };
+        let mut key_rows = vec![];
         for (cpu, value) in values.iter_mut().enumerate() {
             let mut row = vec![];
-            if percpu {
-                row.push(cpu.to_string());
+            if split {
+                row.push(Cell::Int(cpu as u64));
             }
-            row.extend(k.row());
-            row.push("│".to_string());
+            row.extend(k.cells());
+            row.push(Cell::Text(SEPARATOR.to_string()));
             value.extend(vec![0; size_of::<V>().saturating_sub(value.len())]);
             let mut v = V::default();
             v.copy_from_bytes(value).unwrap();
-            row.extend(v.row());
-            rows.push(row);
+            row.extend(v.cells());
+            key_rows.push(row);
+        }
+        if percpu == Some(PercpuMode::Sum) {
+            key_rows = sum(key_rows, K::header().len() + 1).into_iter().collect();
+        }
+        for row in key_rows {
+            table.rows.push(row);
+            table.keys.push(raw_key.clone());
+        }
+    }
+    Ok(table)
+}
+
+/// 把同一个 key 在各个 CPU 上的 value 加起来，不是计数的列取第一个 CPU 的值
+fn sum(rows: Vec<Vec<Cell>>, skip: usize) -> Option<Vec<Cell>> {
+    rows.into_iter().reduce(|mut total, row| {
+        for (total, cell) in total.iter_mut().zip(row).skip(skip) {
+            if let Some(sum) = total.add(&cell) {
+                *total = sum;
+            }
+        }
+        total
+    })
+}
+
+/// 按 map 名字选择 key/value 类型，不支持的 map 返回 None。
+/// `limit` 是交互界面最多显示的条目数，命令行输出时是 None
+pub fn decode(
+    name: &str,
+    map: &MapHandle,
+    config: &Config,
+    limit: Option<usize>,
+) -> Result<Option<MapTable>> {
+    let percpu = Some(config.percpu(name));
+    // 按过期时间过滤 ct 时先读取所有条目，过滤之后再截断
+    let within = config.expires_within.filter(|_| name.starts_with("ct"));
+    let read_limit = if within.is_some() { None } else { limit };
+    let mut table = match name {
+        "ipcache" => dump::<IpcacheKey, RemoteEndpointInfo>(map, None, read_limit)?,
+        "metrics" => dump::<MetricsKey, MetricsValue>(map, percpu, read_limit)?,
+        "tunnel map" => dump::<TunnelKey, TunnelValue>(map, None, read_limit)?,
+        "ct4 global" | "ct any4 global" => dump::<Ipv4CtTuple, CtEntry>(map, None, read_limit)?,
+        "ct6 global" | "ct any6 global" => dump::<Ipv6CtTuple, CtEntry>(map, None, read_limit)?,
+        "lb4 reverse nat" => dump::<Lb4ReverseNatKey, Lb4ReverseNat>(map, None, read_limit)?,
+        "lb4 reverse sk" => dump::<Ipv4RevnatTuple, Ipv4RevnatEntry>(map, None, read_limit)?,
+        "lb4 services v2" => dump::<Lb4Key, Lb4Service>(map, None, read_limit)?,
+        "snat v4 external" => dump::<Ipv4CtTuple, Ipv4NatEntry>(map, None, read_limit)?,
+        "snat v6 external" => dump::<Ipv6CtTuple, Ipv6NatEntry>(map, None, read_limit)?,
+        "lb4 backends v3" => dump::<Lb4BackendKey, Lb4Backend>(map, None, read_limit)?,
+        // lb6 的 reverse nat 和 backend 的 key 和 lb4 一样
+        "lb6 reverse nat" => dump::<Lb4ReverseNatKey, Lb6ReverseNat>(map, None, read_limit)?,
+        "lb6 reverse sk" => dump::<Ipv6RevnatTuple, Ipv6RevnatEntry>(map, None, read_limit)?,
+        "lb6 services v2" => dump::<Lb6Key, Lb6Service>(map, None, read_limit)?,
+        "lb6 backends v3" => dump::<Lb4BackendKey, Lb6Backend>(map, None, read_limit)?,
+        "lxc" => dump::<EndpointKey, EndpointInfo>(map, None, read_limit)?,
+        "egress gw policy v4" => {
+            dump::<EgressGwPolicyKey, EgressGwPolicyEntry>(map, None, read_limit)?
+        }
+        "lb4 source range" => dump::<Lb4SrcRangeKey, SrcRangeValue>(map, None, read_limit)?,
+        "lb6 source range" => dump::<Lb6SrcRangeKey, SrcRangeValue>(map, None, read_limit)?,
+        "encrypt state" => encrypt_state(map, config)?,
+        "auth map" => dump::<AuthKey, AuthInfo>(map, None, read_limit)?,
+        "throttle" => dump::<EdtId, EdtInfo>(map, None, read_limit)?,
+        "vtep map" => dump::<VtepKey, VtepValue>(map, None, read_limit)?,
+        "srv6 vrf v4" => dump::<Srv6VrfKey4, Srv6VrfValue>(map, None, read_limit)?,
+        "srv6 vrf v6" => dump::<Srv6VrfKey6, Srv6VrfValue>(map, None, read_limit)?,
+        "srv6 policy v4" => dump::<Srv6PolicyKey4, Srv6Sid>(map, None, read_limit)?,
+        "srv6 policy v6" => dump::<Srv6PolicyKey6, Srv6Sid>(map, None, read_limit)?,
+        "srv6 state v4" => dump::<Srv6Ipv4_2tuple, Srv6Ipv6_2tuple>(map, None, read_limit)?,
+        "srv6 state v6" => dump::<Srv6Ipv6_2tuple, Srv6Ipv6_2tuple>(map, None, read_limit)?,
+        _ => {
+            if name.starts_with("policy") {
+                dump::<PolicyKey, PolicyEntry>(map, None, read_limit)?
+            } else {
+                return Ok(None);
+            }
         }
+    };
+    icmp_ids(&mut table);
+    match name {
+        "throttle" => {
+            let endpoints = endpoints(config);
+            table.add_column("id", "endpoint", |id| match id {
+                Cell::Int(id) => match endpoints.get(id) {
+                    Some(addrs) => Cell::Text(addrs.join(", ")),
+                    None => Cell::from("-"),
+                },
+                _ => Cell::from("-"),
+            });
+        }
+        "lb4 source range" | "lb6 source range" => {
+            let services = services(config, &name[..3]);
+            table.add_column("rev_nat_id", "service", |id| match id {
+                Cell::Int(id) => match services.get(id) {
+                    Some(service) => Cell::Text(service.clone()),
+                    None => Cell::from("-"),
+                },
+                _ => Cell::from("-"),
+            });
+        }
+        _ => {}
+    }
+    resolve_times(&mut table, name, config)?;
+    table.set_number_format(config.numbers.unwrap_or_default());
+    if let Some(secs) = within {
+        expires_within(&mut table, secs);
+        if let Some(limit) = limit {
+            table.truncate(limit);
+        }
+    }
+    if let Some(map) = config.map(name) {
+        table.hide(&map.hidden);
+    }
+    Ok(Some(table))
+}
+
+/// ICMP 的 ct/nat 条目没有端口，端口的位置放的是 echo 的 identifier
+fn icmp_ids(table: &mut MapTable) {
+    let Some(nexthdr) = table.column("nexthdr") else {
+        return;
+    };
+    let ports: Vec<_> = (0..table.header.len())
+        .filter(|&i| matches!(table.header[i].as_str(), "dport" | "sport" | "to_port"))
+        .collect();
+    for row in table.rows.iter_mut() {
+        let Cell::Enum { value, .. } = row[nexthdr] else {
+            continue;
+        };
+        if !matches!(value as u8, IPPROTO_ICMP | IPPROTO_ICMPV6) {
+            continue;
+        }
+        for &i in &ports {
+            if let Cell::Port(id) = row[i] {
+                row[i] = Cell::IcmpId(id);
+            }
+        }
+    }
+}
+
+/// encrypt state 里当前的 key，后面加上 tunnel map、lxc 和 ipcache 里用到 key 的条目，
+/// 用来检查 key 轮换时还有哪些对端在用旧的 key
+fn encrypt_state(map: &MapHandle, config: &Config) -> Result<MapTable> {
+    let state = dump::<ArrayIndex, EncryptConfig>(map, None, None)?;
+    let current = state.rows.first().and_then(|row| match row.last() {
+        Some(Cell::Int(key)) => Some(*key),
+        _ => None,
+    });
+    let mut table = MapTable {
+        header: ["map", "entry", SEPARATOR, "key", "current"]
+            .map(String::from)
+            .to_vec(),
+        ..Default::default()
+    };
+    let mut push = |map: &str, entry: String, key: u64| {
+        table.rows.push(vec![
+            Cell::from(map),
+            Cell::Text(entry),
+            Cell::Text(SEPARATOR.to_string()),
+            Cell::Int(key),
+            Cell::Bool(Some(key) == current),
+        ]);
+        // encrypt state 是 array map，不能删除；其它条目不属于这个 map，也不能在这里删除
+        table.keys.push(vec![]);
+    };
+    if let Some(key) = current {
+        push("encrypt state", "index 0".to_string(), key);
+    }
+    let users = [
+        (
+            "tunnel map",
+            key_users::<TunnelKey, TunnelValue>(config, "tunnel map"),
+        ),
+        ("lxc", key_users::<EndpointKey, EndpointInfo>(config, "lxc")),
+        (
+            "ipcache",
+            key_users::<IpcacheKey, RemoteEndpointInfo>(config, "ipcache"),
+        ),
+    ];
+    for (map, users) in users {
+        // key 是 0 的条目不加密
+        for (entry, key) in users.into_iter().filter(|(_, key)| *key != 0) {
+            push(map, entry, key);
+        }
+    }
+    Ok(table)
+}
+
+/// 读取用于查找的另一个 map 的所有条目，map 不存在或读不了时返回 None
+fn lookup<K, V>(config: &Config, name: &str) -> Option<MapTable>
+where
+    K: TuiTable + Default + Plain,
+    V: TuiTable + Default + Plain,
+{
+    let (_, path) = find(config, name).ok()?;
+    dump::<K, V>(&MapHandle::from_pinned_path(path).ok()?, None, None).ok()
+}
+
+/// 另一个 map 里每个条目的 `addr` 和 `key` 列，map 不存在时返回空
+fn key_users<K, V>(config: &Config, name: &str) -> Vec<(String, u64)>
+where
+    K: TuiTable + Default + Plain,
+    V: TuiTable + Default + Plain,
+{
+    let Some(table) = lookup::<K, V>(config, name) else {
+        return vec![];
+    };
+    let (Some(addr), Some(key)) = (table.column("addr"), table.column("key")) else {
+        return vec![];
+    };
+    table
+        .rows
+        .iter()
+        .filter_map(|row| match row[key] {
+            Cell::Int(k) => Some((row[addr].to_string(), k)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 从 `lb4 reverse nat` 或 `lb6 reverse nat` 读取每个 rev_nat_index 对应的服务地址，读不到时返回空
+fn services(config: &Config, family: &str) -> HashMap<u64, String> {
+    let table = match family {
+        "lb4" => lookup::<Lb4ReverseNatKey, Lb4ReverseNat>(config, "lb4 reverse nat"),
+        _ => lookup::<Lb4ReverseNatKey, Lb6ReverseNat>(config, "lb6 reverse nat"),
+    };
+    let Some(table) = table else {
+        return HashMap::new();
+    };
+    let (Some(index), Some(address), Some(port)) = (
+        table.column("rev_nat_index"),
+        table.column("address"),
+        table.column("port"),
+    ) else {
+        return HashMap::new();
+    };
+    table
+        .rows
+        .iter()
+        .filter_map(|row| match (&row[index], &row[address], &row[port]) {
+            (Cell::Int(id), Cell::Ip(ip), Cell::Port(port)) => {
+                Some((*id, SocketAddr::new(*ip, *port).to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// 从 lxc map 读取每个 endpoint id 对应的地址，读不到 lxc map 时返回空
+fn endpoints(config: &Config) -> HashMap<u64, Vec<String>> {
+    let mut endpoints: HashMap<u64, Vec<String>> = HashMap::new();
+    let Some(table) = lookup::<EndpointKey, EndpointInfo>(config, "lxc") else {
+        return endpoints;
+    };
+    let (Some(addr), Some(lxc_id)) = (table.column("addr"), table.column("lxc_id")) else {
+        return endpoints;
+    };
+    for row in &table.rows {
+        if let Cell::Int(id) = row[lxc_id] {
+            endpoints.entry(id).or_default().push(row[addr].to_string());
+        }
+    }
+    endpoints
+}
+
+/// 把 `Cell::Timestamp` 换算成 `Cell::Time`。读不到当前时间时（比如不是 root）保留原始值，
+/// 只有 ct 要按 `expires_within` 过滤时返回错误
+fn resolve_times(table: &mut MapTable, name: &str, config: &Config) -> Result<()> {
+    let has_times = table
+        .rows
+        .iter()
+        .flatten()
+        .any(|cell| matches!(cell, Cell::Timestamp { .. }));
+    if !has_times {
+        return Ok(());
+    }
+    let node_config = config
+        .node_config
+        .strip_prefix("/")
+        .unwrap_or(&config.node_config);
+    let mono = MonoClock::detect(&roots(config)[0].join(node_config));
+    let clock = match Clock::now(mono) {
+        Ok(clock) => clock,
+        // 只有 ct 需要按时间过滤
+        Err(e) if name.starts_with("ct") && config.expires_within.is_some() => return Err(e),
+        Err(_) => return Ok(()),
+    };
+    for cell in table.rows.iter_mut().flatten() {
+        if let Cell::Timestamp { value, source } = *cell {
+            *cell = clock.resolve(value, source, config.iso_time);
+        }
+    }
+    Ok(())
+}
+
+/// 只保留 `expires_within` 秒内过期的 ct 条目
+fn expires_within(table: &mut MapTable, secs: u64) {
+    let lifetime = table.column("lifetime");
+    let within = i64::try_from(secs)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(1_000_000_000);
+    table.retain(|row| match lifetime.map(|i| &row[i]) {
+        Some(Cell::Time { at, now, .. }) => at - now <= within,
+        _ => true,
+    });
+}
+
+/// 列出匹配 `map_filter` 的 map
+pub fn list(config: &Config) -> Result<Vec<(String, PathBuf)>> {
+    let map_filter = Regex::new(&config.map_filter)?;
+    let mut maps = pinned(config)?;
+    maps.retain(|(name, _)| map_filter.is_match(name));
+    Ok(maps)
+}
+
+/// cilium-agent 进程的根目录。
+/// 没有指定 pid 时查找所有 cilium-agent 进程，找不到时使用本机的根目录
+fn roots(config: &Config) -> Vec<PathBuf> {
+    match config.pid {
+        Some(pid) => vec![PathBuf::from(format!("/proc/{}/root", pid))],
+        None => {
+            let system = System::new_with_specifics(
+                RefreshKind::new().with_processes(ProcessRefreshKind::new()),
+            );
+            let roots: Vec<_> = system
+                .processes_by_name("cilium-agent")
+                .map(|process| PathBuf::from(format!("/proc/{}/root", process.pid().as_u32())))
+                .collect();
+            if roots.is_empty() {
+                vec![PathBuf::from("/")]
+            } else {
+                roots
+            }
+        }
+    }
+}
+
+/// 列出 cilium-agent 的 bpffs 下所有的 map，名字去掉 `cilium_` 前缀并把 `_` 换成空格
+fn pinned(config: &Config) -> Result<Vec<(String, PathBuf)>> {
+    let bpffs = config.bpffs.strip_prefix("/").unwrap_or(&config.bpffs);
+    let mut maps = vec![];
+    for root in roots(config) {
+        let bpf_path = root.join(bpffs);
+        for entry in std::fs::read_dir(&bpf_path)
+            .with_context(|| format!("failed to read {}", bpf_path.display()))?
+        {
+            let dir = entry?;
+            let name = dir.file_name().to_string_lossy().to_string();
+            let name = name
+                .strip_prefix("cilium_")
+                .unwrap_or(&name)
+                .replace('_', " ");
+            maps.push((name, dir.path()));
+        }
+    }
+    Ok(maps.into_iter().sorted().collect())
+}
+
+/// 按名字查找 map，名字可以写成 `ct4 global`、`ct4_global`、`cilium_ct4_global` 或者 pin 的绝对路径
+pub fn find(config: &Config, name: &str) -> Result<(String, PathBuf)> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let name = file_name.strip_prefix("cilium_").unwrap_or(&file_name);
+        return Ok((name.replace('_', " "), path.to_path_buf()));
+    }
+    let wanted = name
+        .strip_prefix("cilium_")
+        .unwrap_or(name)
+        .replace('_', " ");
+    match pinned(config)?
+        .into_iter()
+        .find(|(name, _)| *name == wanted)
+    {
+        Some(map) => Ok(map),
+        None => bail!("map {:?} not found", name),
     }
-    Ok((rows, header))
 }
@@ -1,3 +1,6 @@
+use std::fmt;
+use tuitable::{Cell, ToCell};
+
 #[repr(C)]
 #[derive(Default)]
 pub struct MetricsReason {
@@ -10,101 +13,140 @@ pub struct MetricsDir {
     dir: u8,
 }
 
-impl ToString for MetricsReason {
-    fn to_string(&self) -> String {
-        match self.reason {
-            0 => "REASON_FORWARDED",
-            3 => "REASON_PLAINTEXT",
-            4 => "REASON_DECRYPT",
-            5 => "REASON_LB_NO_BACKEND_SLOT",
-            6 => "REASON_LB_NO_BACKEND",
-            7 => "REASON_LB_REVNAT_UPDATE",
-            8 => "REASON_LB_REVNAT_STALE",
-            9 => "REASON_FRAG_PACKET",
-            10 => "REASON_FRAG_PACKET_UPDATE",
-            11 => "REASON_MISSED_CUSTOM_CALL",
-            // DROP
-            130 => "DROP_UNUSED1",
-            131 => "DROP_UNUSED2",
-            132 => "DROP_INVALID_SIP",
-            133 => "DROP_POLICY",
-            134 => "DROP_INVALID",
-            135 => "DROP_CT_INVALID_HDR",
-            136 => "DROP_FRAG_NEEDED",
-            137 => "DROP_CT_UNKNOWN_PROTO",
-            138 => "DROP_UNUSED4",
-            139 => "DROP_UNKNOWN_L3",
-            140 => "DROP_MISSED_TAIL_CALL",
-            141 => "DROP_WRITE_ERROR",
-            142 => "DROP_UNKNOWN_L4",
-            143 => "DROP_UNKNOWN_ICMP_CODE",
-            144 => "DROP_UNKNOWN_ICMP_TYPE",
-            145 => "DROP_UNKNOWN_ICMP6_CODE",
-            146 => "DROP_UNKNOWN_ICMP6_TYPE",
-            147 => "DROP_NO_TUNNEL_KEY",
-            148 => "DROP_UNUSED5",
-            149 => "DROP_UNUSED6",
-            150 => "DROP_UNKNOWN_TARGET",
-            151 => "DROP_UNROUTABLE",
-            152 => "DROP_UNUSED7",
-            153 => "DROP_CSUM_L3",
-            154 => "DROP_CSUM_L4",
-            155 => "DROP_CT_CREATE_FAILED",
-            156 => "DROP_INVALID_EXTHDR",
-            157 => "DROP_FRAG_NOSUPPORT",
-            158 => "DROP_NO_SERVICE",
-            159 => "DROP_UNUSED8",
-            160 => "DROP_NO_TUNNEL_ENDPOINT",
-            161 => "DROP_NAT_46X64_DISABLED",
-            162 => "DROP_EDT_HORIZON",
-            163 => "DROP_UNKNOWN_CT",
-            164 => "DROP_HOST_UNREACHABLE",
-            165 => "DROP_NO_CONFIG",
-            166 => "DROP_UNSUPPORTED_L2",
-            167 => "DROP_NAT_NO_MAPPING",
-            168 => "DROP_NAT_UNSUPP_PROTO",
-            169 => "DROP_NO_FIB",
-            170 => "DROP_ENCAP_PROHIBITED",
-            171 => "DROP_INVALID_IDENTITY",
-            172 => "DROP_UNKNOWN_SENDER",
-            173 => "DROP_NAT_NOT_NEEDED",
-            174 => "DROP_IS_CLUSTER_IP",
-            175 => "DROP_FRAG_NOT_FOUND",
-            176 => "DROP_FORBIDDEN_ICMP6",
-            177 => "DROP_NOT_IN_SRC_RANGE",
-            178 => "DROP_PROXY_LOOKUP_FAILED",
-            179 => "DROP_PROXY_SET_FAILED",
-            180 => "DROP_PROXY_UNKNOWN_PROTO",
-            181 => "DROP_POLICY_DENY",
-            182 => "DROP_VLAN_FILTERED",
-            183 => "DROP_INVALID_VNI",
-            184 => "DROP_INVALID_TC_BUFFER",
-            185 => "DROP_NO_SID",
-            186 => "DROP_MISSING_SRV6_STATE",
-            187 => "DROP_NAT46",
-            188 => "DROP_NAT64",
-            189 => "DROP_POLICY_AUTH_REQUIRED",
-            190 => "DROP_CT_NO_MAP_FOUND",
-            191 => "DROP_SNAT_NO_MAP_FOUND",
-            192 => "DROP_INVALID_CLUSTER_ID",
-            193 => "DROP_DSR_ENCAP_UNSUPP_PROTO",
-            194 => "DROP_NO_EGRESS_GATEWAY",
-            195 => "DROP_UNENCRYPTED_TRAFFIC",
-            100 => "NAT_46X64_RECIRC",
-            _ => "",
+/// cilium 的 `REASON_*`/`DROP_*`：代码、名字、一行说明和常见的原因/处理方法
+const REASONS: &[(u8, &str, &str, &str)] = &[
+    (0, "REASON_FORWARDED", "Packet forwarded", "Normal traffic, nothing to fix"),
+    (3, "REASON_PLAINTEXT", "Forwarded without encryption", "Expected unless IPsec or WireGuard should cover this traffic; check the encryption config"),
+    (4, "REASON_DECRYPT", "Decrypted and forwarded", "Normal with IPsec or WireGuard enabled"),
+    (5, "REASON_LB_NO_BACKEND_SLOT", "No backend slot for the service", "Backends changed during the lookup; usually transient"),
+    (6, "REASON_LB_NO_BACKEND", "Service backend not found", "A backend was removed while in use; check the endpoints of the service"),
+    (7, "REASON_LB_REVNAT_UPDATE", "Reverse NAT entry updated", "Informational"),
+    (8, "REASON_LB_REVNAT_STALE", "Stale reverse NAT entry", "The service was recreated; old connections get reset"),
+    (9, "REASON_FRAG_PACKET", "Fragmented packet", "Informational; enable fragment tracking for load balancing fragments"),
+    (10, "REASON_FRAG_PACKET_UPDATE", "Fragment tracking entry updated", "Informational"),
+    (11, "REASON_MISSED_CUSTOM_CALL", "Custom program tail call missed", "No custom BPF program installed at this hook; informational"),
+    // DROP
+    (130, "DROP_UNUSED1", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (131, "DROP_UNUSED2", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (132, "DROP_INVALID_SIP", "Invalid source IP", "Spoofed or misrouted traffic from an endpoint; check the pod IP assignment"),
+    (133, "DROP_POLICY", "Denied by network policy", "No policy allows this traffic; inspect the policies and the policy map of the endpoint"),
+    (134, "DROP_INVALID", "Invalid packet", "Malformed or truncated packet"),
+    (135, "DROP_CT_INVALID_HDR", "Invalid header for conntrack", "Truncated L4 header or unsupported options"),
+    (136, "DROP_FRAG_NEEDED", "Fragmentation needed", "Packet exceeds the MTU with DF set; check the MTU config and PMTU discovery"),
+    (137, "DROP_CT_UNKNOWN_PROTO", "L4 protocol not supported by conntrack", "Only TCP, UDP, ICMP and SCTP are tracked; e.g. GRE or ESP through the datapath is dropped"),
+    (138, "DROP_UNUSED4", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (139, "DROP_UNKNOWN_L3", "Unknown L3 protocol", "Non-IP ethertype, or IPv4/IPv6 support is disabled"),
+    (140, "DROP_MISSED_TAIL_CALL", "BPF tail call missed", "Programs being replaced during an agent restart; persistent counts mean a missing program or failed compile"),
+    (141, "DROP_WRITE_ERROR", "Failed to write packet", "Packet rewrite failed, often because of missing headroom"),
+    (142, "DROP_UNKNOWN_L4", "Unknown L4 protocol", "Protocol not supported by policy enforcement"),
+    (143, "DROP_UNKNOWN_ICMP_CODE", "Unknown ICMP code", "ICMP message not handled by the datapath"),
+    (144, "DROP_UNKNOWN_ICMP_TYPE", "Unknown ICMP type", "ICMP message not handled by the datapath"),
+    (145, "DROP_UNKNOWN_ICMP6_CODE", "Unknown ICMPv6 code", "ICMPv6 message not handled by the datapath"),
+    (146, "DROP_UNKNOWN_ICMP6_TYPE", "Unknown ICMPv6 type", "ICMPv6 message not handled by the datapath"),
+    (147, "DROP_NO_TUNNEL_KEY", "No encryption key for the tunnel", "IPsec key missing; check the cilium-ipsec-keys secret"),
+    (148, "DROP_UNUSED5", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (149, "DROP_UNUSED6", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (150, "DROP_UNKNOWN_TARGET", "Unknown target", "Destination endpoint not found on this node; check the lxc map and ipcache"),
+    (151, "DROP_UNROUTABLE", "Unroutable destination", "No route for the destination; check ipcache and the routing mode"),
+    (152, "DROP_UNUSED7", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (153, "DROP_CSUM_L3", "Bad L3 checksum", "Corrupted packet or a checksum offload problem"),
+    (154, "DROP_CSUM_L4", "Bad L4 checksum", "Corrupted packet or a checksum offload problem"),
+    (155, "DROP_CT_CREATE_FAILED", "Failed to create conntrack entry", "CT map is full; raise bpf-ct-global-*-max or tune CT garbage collection"),
+    (156, "DROP_INVALID_EXTHDR", "Invalid IPv6 extension header", "Malformed or unsupported extension header chain"),
+    (157, "DROP_FRAG_NOSUPPORT", "Fragments not supported", "Enable IPv4 fragment tracking"),
+    (158, "DROP_NO_SERVICE", "No service found", "Service frontend missing from the lb maps; compare with `cilium service list`"),
+    (159, "DROP_UNUSED8", "Unused drop code", "Should not occur; check that agent and datapath versions match"),
+    (160, "DROP_NO_TUNNEL_ENDPOINT", "No tunnel endpoint for the destination", "Remote node missing from the tunnel map/ipcache; check node connectivity and the remote agent"),
+    (161, "DROP_NAT_46X64_DISABLED", "NAT46/64 disabled", "Enable NAT46/64 support"),
+    (162, "DROP_EDT_HORIZON", "Over the bandwidth manager time horizon", "The pod exceeds its egress bandwidth limit"),
+    (163, "DROP_UNKNOWN_CT", "Unknown conntrack state", "No valid CT state, e.g. a reply without a request or an expired entry"),
+    (164, "DROP_HOST_UNREACHABLE", "Host unreachable", "Check host routing and node addresses"),
+    (165, "DROP_NO_CONFIG", "Missing configuration", "Endpoint config not loaded yet; usually transient during regeneration"),
+    (166, "DROP_UNSUPPORTED_L2", "Unsupported L2 protocol", "Non-Ethernet frames on this device"),
+    (167, "DROP_NAT_NO_MAPPING", "No NAT mapping", "Reply for a connection whose SNAT entry expired or was never created"),
+    (168, "DROP_NAT_UNSUPP_PROTO", "Protocol not supported by NAT", "Only TCP, UDP, ICMP and SCTP can be masqueraded"),
+    (169, "DROP_NO_FIB", "FIB lookup failed", "No route or neighbour entry for the destination"),
+    (170, "DROP_ENCAP_PROHIBITED", "Encapsulation prohibited", "Traffic would be tunneled but encapsulation is disabled"),
+    (171, "DROP_INVALID_IDENTITY", "Invalid security identity", "Unknown or reserved identity; check ipcache and identity allocation"),
+    (172, "DROP_UNKNOWN_SENDER", "Unknown sender", "Source is not a known endpoint"),
+    (173, "DROP_NAT_NOT_NEEDED", "NAT not needed", "Informational; the packet did not need SNAT"),
+    (174, "DROP_IS_CLUSTER_IP", "ClusterIP accessed from outside the cluster", "ClusterIPs are only reachable in-cluster; use a NodePort or LoadBalancer"),
+    (175, "DROP_FRAG_NOT_FOUND", "Fragment tracking entry not found", "First fragment missing or the fragment map is full"),
+    (176, "DROP_FORBIDDEN_ICMP6", "Forbidden ICMPv6 message", "ICMPv6 type not allowed by policy"),
+    (177, "DROP_NOT_IN_SRC_RANGE", "Source not in LoadBalancer source ranges", "Add the client to loadBalancerSourceRanges"),
+    (178, "DROP_PROXY_LOOKUP_FAILED", "Proxy socket lookup failed", "L7 proxy not listening; check Envoy or the DNS proxy"),
+    (179, "DROP_PROXY_SET_FAILED", "Failed to assign proxy socket", "L7 proxy socket problem; check Envoy or the DNS proxy"),
+    (180, "DROP_PROXY_UNKNOWN_PROTO", "Protocol not supported by proxy redirect", "Only TCP and UDP can be redirected to the proxy"),
+    (181, "DROP_POLICY_DENY", "Denied by a deny policy", "An explicit deny rule matched"),
+    (182, "DROP_VLAN_FILTERED", "VLAN filtered", "VLAN id not allowed; see the vlan-bpf-bypass option"),
+    (183, "DROP_INVALID_VNI", "Invalid VNI", "Tunnel packet with an unexpected VNI"),
+    (184, "DROP_INVALID_TC_BUFFER", "Invalid tc buffer", "Datapath bug or kernel incompatibility"),
+    (185, "DROP_NO_SID", "No SRv6 SID", "No SID in the SRv6 policy map for the destination"),
+    (186, "DROP_MISSING_SRV6_STATE", "Missing SRv6 state", "SRv6 state map entry missing"),
+    (187, "DROP_NAT46", "NAT46 translation failed", "Check the NAT46/64 prefix config"),
+    (188, "DROP_NAT64", "NAT64 translation failed", "Check the NAT46/64 prefix config"),
+    (189, "DROP_POLICY_AUTH_REQUIRED", "Authentication required", "Policy requires auth and the auth map has no valid entry; check SPIRE and the auth map"),
+    (190, "DROP_CT_NO_MAP_FOUND", "Conntrack map not found", "Per-cluster CT map missing (cluster mesh)"),
+    (191, "DROP_SNAT_NO_MAP_FOUND", "SNAT map not found", "Per-cluster SNAT map missing (cluster mesh)"),
+    (192, "DROP_INVALID_CLUSTER_ID", "Invalid cluster id", "Cluster mesh cluster id out of range"),
+    (193, "DROP_DSR_ENCAP_UNSUPP_PROTO", "Protocol not supported by DSR encapsulation", "Only TCP and UDP work with DSR encapsulation"),
+    (194, "DROP_NO_EGRESS_GATEWAY", "No egress gateway", "An egress gateway policy matched but no gateway is available"),
+    (195, "DROP_UNENCRYPTED_TRAFFIC", "Unencrypted traffic", "Strict encryption mode dropped plaintext traffic"),
+    (100, "NAT_46X64_RECIRC", "NAT46/64 recirculation", "Informational"),
+];
+
+impl MetricsReason {
+    fn lookup(&self) -> Option<&'static (u8, &'static str, &'static str, &'static str)> {
+        REASONS.iter().find(|(code, ..)| *code == self.reason)
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        self.lookup().map(|(_, name, ..)| *name)
+    }
+
+    /// 一行说明和常见的原因/处理方法
+    pub fn help(&self) -> Option<(&'static str, &'static str)> {
+        self.lookup().map(|(_, _, summary, fix)| (*summary, *fix))
+    }
+}
+
+impl fmt::Display for MetricsReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_cell().fmt(f)
+    }
+}
+
+impl ToCell for MetricsReason {
+    fn to_cell(&self) -> Cell {
+        Cell::Enum {
+            value: self.reason as u64,
+            name: self.name(),
         }
-        .to_string()
     }
 }
 
-impl ToString for MetricsDir {
-    fn to_string(&self) -> String {
-        match self.dir {
+impl MetricsDir {
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.dir {
             1 => "INGRESS",
             2 => "EGRESS",
             3 => "SERVICE",
-            _ => "",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for MetricsDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_cell().fmt(f)
+    }
+}
+
+impl ToCell for MetricsDir {
+    fn to_cell(&self) -> Cell {
+        Cell::Enum {
+            value: self.dir as u64,
+            name: self.name(),
         }
-        .to_string()
     }
 }
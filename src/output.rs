@@ -0,0 +1,76 @@
+use crate::{MapTable, SEPARATOR};
+use clap::ValueEnum;
+use std::io::{Result, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// 对齐的文本表格
+    #[default]
+    Table,
+    Csv,
+    /// 数组，每个元素是 `{"key": {..}, "value": {..}}`，per-CPU 的 map 还有 `cpu`
+    Json,
+}
+
+pub fn write(w: &mut impl Write, table: &MapTable, format: Format) -> Result<()> {
+    match format {
+        Format::Table => write_table(w, table),
+        Format::Csv => write_csv(w, table),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *w, &table.entries())?;
+            writeln!(w)
+        }
+    }
+}
+
+fn write_table(w: &mut impl Write, table: &MapTable) -> Result<()> {
+    let rows: Vec<_> = rows(table).collect();
+    let widths: Vec<_> = (0..table.header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in rows {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(w, "{}", line.join("  ").trim_end())?;
+    }
+    Ok(())
+}
+
+fn write_csv(w: &mut impl Write, table: &MapTable) -> Result<()> {
+    // 分隔 key 和 value 的那一列在 csv 里没有意义
+    let columns: Vec<_> = (0..table.header.len())
+        .filter(|&i| table.header[i] != SEPARATOR)
+        .collect();
+    for row in rows(table) {
+        let line: Vec<_> = columns.iter().map(|&i| csv_field(&row[i])).collect();
+        writeln!(w, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+/// 表头和每一行渲染成文本
+fn rows(table: &MapTable) -> impl Iterator<Item = Vec<String>> + '_ {
+    let header = table.header.clone();
+    std::iter::once(header).chain(
+        table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect()),
+    )
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -1,10 +1,26 @@
+use std::fmt;
+use tuitable::{Cell, ToCell};
+
 #[derive(Default)]
 pub struct Port {
     port: u16,
 }
 
-impl ToString for Port {
-    fn to_string(&self) -> String {
-        u16::from_be(self.port).to_string()
+impl Port {
+    /// 主机字节序的端口
+    pub fn get(&self) -> u16 {
+        u16::from_be(self.port)
+    }
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl ToCell for Port {
+    fn to_cell(&self) -> Cell {
+        Cell::Port(self.get())
     }
 }
@@ -1,60 +1,59 @@
 use crate::*;
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
-use itertools::Itertools;
-use lazy_static::lazy_static;
+use crossterm::event::{self, Event, KeyEventKind};
 use libbpf_rs::MapHandle;
 use ratatui::{prelude::*, widgets::*};
-use regex::Regex;
 use std::path::PathBuf;
-use sysinfo::{ProcessExt, ProcessRefreshKind, RefreshKind, System, SystemExt};
+use std::time::Duration;
+use tuitable::NumberFormat;
+
+/// 交互界面最多显示的条目数
+const MAX_ROWS: usize = 100;
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
+    let keymap = app.config.keymap()?;
+    let refresh = Duration::from_secs(app.config.refresh_interval);
     app.list()?;
+    if let Some(name) = app.initial_map.take() {
+        app.open(&name)?;
+    }
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
 
+        if !refresh.is_zero() && !event::poll(refresh)? {
+            app.refresh()?;
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
-                match (key.code, key.modifiers) {
-                    (KeyCode::Char('q'), KeyModifiers::NONE) => return Ok(()),
-                    (KeyCode::Char('j'), KeyModifiers::NONE) => app.next_map(),
-                    (KeyCode::Char('k'), KeyModifiers::NONE) => app.previous_map(),
-                    (KeyCode::Char('j'), KeyModifiers::CONTROL) => app.next_row(),
-                    (KeyCode::Char('k'), KeyModifiers::CONTROL) => app.previous_row(),
-                    (KeyCode::Char('l'), KeyModifiers::NONE) => {
+                app.message = None;
+                match keymap.action(&key) {
+                    Some(Action::Quit) => return Ok(()),
+                    Some(Action::NextMap) => app.next_map(),
+                    Some(Action::PreviousMap) => app.previous_map(),
+                    Some(Action::NextRow) => app.next_row(),
+                    Some(Action::PreviousRow) => app.previous_row(),
+                    Some(Action::Open) => {
                         if let Some(selected_map) = app.list_state.selected() {
                             // let (_, path) = &app.maps[selected_map];
                             app.get(selected_map)?;
                             app.content_state.select(None);
                         }
                     }
-                    (KeyCode::Char('h'), KeyModifiers::NONE) => app.list()?,
-                    _ => {}
+                    Some(Action::List) => app.list()?,
+                    Some(Action::Delete) => app.delete()?,
+                    Some(Action::Sort) => app.sort_next(),
+                    Some(Action::ReverseSort) => app.reverse_sort(),
+                    Some(Action::Detail) => app.detail = !app.detail,
+                    Some(Action::ToggleTime) => app.toggle_time(),
+                    Some(Action::CycleNumbers) => app.cycle_numbers(),
+                    None => {}
                 }
             }
         }
     }
 }
 
-lazy_static! {
-    static ref MAP_FILTER: Regex = Regex::new(
-        "^(policy\
-         |tunnel map\
-         |ct4 global\
-         |metrics\
-         |ct any4 global\
-         |lb4 reverse nat\
-         |lb4 reverse sk\
-         |lb4 services v2\
-         |lb4 backends v3\
-         |snat v4 external\
-         |lxc\
-         |ipcache)"
-    )
-    .unwrap();
-}
-
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let name_len = app
         .maps
@@ -75,34 +74,78 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .split(f.size());
 
     let selected_style = Style::default().add_modifier(Modifier::REVERSED);
-    let header = Row::new(app.header.clone())
-        .style(Style::default().add_modifier(Modifier::BOLD))
-        .bottom_margin(1);
-    let rows = app.rows.iter().map(|item| Row::new(item.clone()));
-    let widths: Vec<_> = (0..app.header.len())
+    let detail = if app.detail { app.detail_lines() } else { None };
+    let table_rect = match &detail {
+        Some(lines) => {
+            let rects = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(5),
+                    Constraint::Length(lines.len() as u16 + 2),
+                ])
+                .split(rects[1]);
+            let detail = Paragraph::new(lines.join("\n"))
+                .block(Block::default().borders(Borders::ALL).title("detail"));
+            f.render_widget(detail, rects[1]);
+            rects[0]
+        }
+        None => rects[1],
+    };
+    let header: Vec<_> = app
+        .table
+        .header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| match app.sort {
+            Some((column, false)) if column == i => format!("{}▲", h),
+            Some((column, true)) if column == i => format!("{}▼", h),
+            _ => h.to_string(),
+        })
+        .collect();
+    let rows: Vec<Vec<_>> = app
+        .table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect();
+    let widths: Vec<_> = (0..header.len())
         .map(|i| {
-            let width = app.header[i].len();
-            app.rows
-                .iter()
-                .map(|row| row[i].len())
+            let width = header[i].chars().count();
+            rows.iter()
+                .map(|row| row[i].chars().count())
                 .max()
                 .unwrap_or(0)
                 .max(width) as _
         })
         .map(Constraint::Length)
         .collect();
+    let header = Row::new(header)
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+    let expired_style = Style::default().fg(Color::Red);
+    let rows = rows.into_iter().enumerate().map(|(i, row)| {
+        if app.table.is_expired(i) {
+            Row::new(row).style(expired_style)
+        } else {
+            Row::new(row)
+        }
+    });
     let t = Table::new(rows)
         .header(header)
         .block(Block::default().borders(Borders::ALL).title(format!(
-            "{} {}/{}",
+            "{} {}/{}{}",
             app.name.clone(),
             app.content_state.selected().map(|i| i + 1).unwrap_or(0),
-            app.rows.len()
+            app.table.rows.len(),
+            app.message
+                .as_ref()
+                .map(|m| format!(" ({})", m))
+                .unwrap_or_default()
         )))
         .widths(&widths)
         .column_spacing(2)
         .highlight_style(selected_style);
-    f.render_stateful_widget(t, rects[1], &mut app.content_state);
+    f.render_stateful_widget(t, table_rect, &mut app.content_state);
 
     let map_names: Vec<_> = app
         .maps
@@ -119,21 +162,50 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     f.render_stateful_widget(maps, rects[0], &mut app.list_state);
 }
 
-#[derive(Default)]
 pub struct App {
     content_state: TableState,
     list_state: ListState,
-    rows: Vec<Vec<String>>,
-    header: Vec<&'static str>,
+    table: MapTable,
     name: String,
     maps: Vec<(String, PathBuf)>,
+    current: Option<usize>,
+    initial_map: Option<String>,
+    message: Option<String>,
+    /// 排序的列和是否倒序
+    sort: Option<(usize, bool)>,
+    /// 是否显示选中行的详情
+    detail: bool,
+    config: Config,
 }
 
 impl App {
+    pub fn new(mut config: Config) -> Result<Self> {
+        config.numbers.get_or_insert(NumberFormat::Units);
+        Ok(Self {
+            content_state: TableState::default(),
+            list_state: ListState::default(),
+            table: MapTable::default(),
+            name: String::new(),
+            maps: vec![],
+            current: None,
+            initial_map: None,
+            message: None,
+            sort: None,
+            detail: false,
+            config,
+        })
+    }
+
+    /// 启动后直接打开这个 map
+    pub fn with_map(mut self, name: Option<String>) -> Self {
+        self.initial_map = name;
+        self
+    }
+
     pub fn next_row(&mut self) {
         let i = match self.content_state.selected() {
             Some(i) => {
-                if i >= self.rows.len() - 1 {
+                if i >= self.table.rows.len() - 1 {
                     0
                 } else {
                     i + 1
@@ -148,7 +220,7 @@ impl App {
         let i = match self.content_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.rows.len() - 1
+                    self.table.rows.len() - 1
                 } else {
                     i - 1
                 }
@@ -190,55 +262,135 @@ impl App {
         // let map = MapHandle::from_map_id(id)?;
         let (name, path) = &self.maps[selected_map];
         let map = MapHandle::from_pinned_path(path)?;
-        let (rows, header) = match name.as_str() {
-            "ipcache" => dump::<IpcacheKey, RemoteEndpointInfo>(&map, false)?,
-            "metrics" => dump::<MetricsKey, MetricsValue>(&map, true)?,
-            "tunnel map" => dump::<TunnelKey, TunnelValue>(&map, false)?,
-            "ct4 global" | "ct any4 global" => dump::<Ipv4CtTuple, CtEntry>(&map, false)?,
-            "lb4 reverse nat" => dump::<Lb4ReverseNatKey, Lb4ReverseNat>(&map, false)?,
-            "lb4 reverse sk" => dump::<Ipv4RevnatTuple, Ipv4RevnatEntry>(&map, false)?,
-            "lb4 services v2" => dump::<Lb4Key, Lb4Service>(&map, false)?,
-            "snat v4 external" => dump::<Ipv4CtTuple, Ipv4NatEntry>(&map, false)?,
-            "lb4 backends v3" => dump::<Lb4BackendKey, Lb4Backend>(&map, false)?,
-            "lxc" => dump::<EndpointKey, EndpointInfo>(&map, false)?,
-            _ => {
-                if name.starts_with("policy") {
-                    dump::<PolicyKey, PolicyEntry>(&map, false)?
-                } else {
-                    self.rows.clear();
-                    self.header.clear();
-                    self.name = "Not supported".to_string();
-                    return Ok(());
+        match decode(name, &map, &self.config, Some(MAX_ROWS))? {
+            Some(table) => {
+                if self.current != Some(selected_map) {
+                    self.sort = None;
                 }
+                self.table = table;
+                self.name = name.to_owned();
+                self.current = Some(selected_map);
+                if let Some((column, reverse)) = self.sort {
+                    self.table.sort(column, reverse);
+                }
+            }
+            None => {
+                self.table = MapTable::default();
+                self.name = "Not supported".to_string();
+                self.current = None;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn open(&mut self, name: &str) -> Result<()> {
+        let (name, path) = find(&self.config, name)?;
+        let selected_map = match self.maps.iter().position(|map| map.0 == name) {
+            Some(i) => i,
+            None => {
+                self.maps.push((name, path));
+                self.maps.len() - 1
             }
         };
-        self.rows = rows;
-        self.header = header;
-        self.name = name.to_owned();
+        self.list_state.select(Some(selected_map));
+        self.get(selected_map)
+    }
+
+    /// 按下一列排序，最后一列之后取消排序
+    pub fn sort_next(&mut self) {
+        let next = self.sort.map(|(column, _)| column + 1).unwrap_or(0);
+        self.sort = (next..self.table.header.len())
+            .find(|&i| self.table.header[i] != SEPARATOR)
+            .map(|column| (column, false));
+        self.resort();
+    }
+
+    pub fn reverse_sort(&mut self) {
+        if let Some((_, reverse)) = self.sort.as_mut() {
+            *reverse = !*reverse;
+        }
+        self.resort();
+    }
+
+    fn resort(&mut self) {
+        if let Some((column, reverse)) = self.sort {
+            self.table.sort(column, reverse);
+        } else if let Some(current) = self.current {
+            // 取消排序时恢复 map 原本的顺序
+            if let Err(e) = self.get(current) {
+                self.message = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn delete(&mut self) -> Result<()> {
+        let (Some(current), Some(selected)) = (self.current, self.content_state.selected()) else {
+            return Ok(());
+        };
+        if !self.config.write {
+            self.message = Some("read-only, restart with --write to delete".to_string());
+            return Ok(());
+        }
+        if self.table.keys[selected].is_empty() {
+            self.message = Some("this entry can not be deleted here".to_string());
+            return Ok(());
+        }
+        let map = MapHandle::from_pinned_path(&self.maps[current].1)?;
+        if let Err(e) = map.delete(&self.table.keys[selected]) {
+            self.message = Some(format!("delete failed: {}", e));
+            return Ok(());
+        }
+        self.refresh()?;
+        self.message = Some("deleted".to_string());
         Ok(())
     }
 
+    pub fn refresh(&mut self) -> Result<()> {
+        if let Some(current) = self.current {
+            self.get(current)?;
+            if let Some(selected) = self.content_state.selected() {
+                if selected >= self.table.rows.len() {
+                    self.content_state
+                        .select(self.table.rows.len().checked_sub(1));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 选中行的每一列占一行，flags 等展开显示
+    fn detail_lines(&self) -> Option<Vec<String>> {
+        let row = self.table.rows.get(self.content_state.selected()?)?;
+        let mut lines = vec![];
+        for (header, cell) in self.table.header.iter().zip(row) {
+            if header == SEPARATOR {
+                continue;
+            }
+            lines.push(format!("{}: {}", header, cell));
+            lines.extend(
+                cell.details()
+                    .into_iter()
+                    .map(|line| format!("    {}", line)),
+            );
+        }
+        Some(lines)
+    }
+
+    pub fn toggle_time(&mut self) {
+        self.config.iso_time = !self.config.iso_time;
+        self.table.set_iso_time(self.config.iso_time);
+    }
+
+    pub fn cycle_numbers(&mut self) {
+        let numbers = self.config.numbers.unwrap_or_default().next();
+        self.config.numbers = Some(numbers);
+        self.table.set_number_format(numbers);
+        self.message = Some(format!("numbers: {}", numbers));
+    }
+
     pub fn list(&mut self) -> Result<()> {
-        let system = System::new_with_specifics(
-            RefreshKind::new().with_processes(ProcessRefreshKind::new()),
-        );
-        let cilium_agent = system.processes_by_name("cilium-agent");
-        self.maps = cilium_agent
-            .flat_map(|process| {
-                let bpf_path = format!("/proc/{}/root/sys/fs/bpf/tc/globals", process.pid());
-                std::fs::read_dir(bpf_path)
-                    .unwrap()
-                    .map(|entry| {
-                        let dir = entry.unwrap();
-                        (
-                            dir.file_name().to_string_lossy().to_string()[7..].replace('_', " "),
-                            dir.path(),
-                        )
-                    })
-                    .filter(|(name, _)| MAP_FILTER.is_match(name))
-            })
-            .sorted()
-            .collect();
+        self.current = None;
+        self.maps = list(&self.config)?;
         Ok(())
     }
 }
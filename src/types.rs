@@ -1,16 +1,21 @@
 use std::fmt;
+use std::net::{IpAddr, SocketAddr};
 
 use crate::{ip::IpFamily, Ip, Ipv4, Ipv6, L4Proto, Mac, MetricsDir, MetricsReason, Port};
 use bitflags::bitflags;
+use tuitable::{Cell, ToCell};
 use tuitable_derive::TuiTable;
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct PolicyKey {
     pad0: u32,
+    #[tuitable(offset = 4)]
     sec_label: u32,
     egress: bool,
     protocol: L4Proto,
+    #[tuitable(offset = 10)]
     dport: Port,
 }
 
@@ -23,161 +28,303 @@ bitflags! {
     }
 }
 
-impl fmt::Display for PolicyEntryFlags {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
-    }
-}
-
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
 pub struct PolicyEntry {
     proxy_port: Port,
     flags: PolicyEntryFlags,
     auth_type: u8,
     pad1: u16,
     pad2: u16,
+    #[tuitable(offset = 8, unit = "count")]
     packets: u64,
+    #[tuitable(unit = "bytes")]
     bytes: u64,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 20)]
 pub struct EndpointKey {
     addr: Ip,
+    #[tuitable(offset = 16)]
     family: IpFamily,
     key: u8,
     cluster_id: u8,
+    pad: u8,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 20)]
 pub struct TunnelKey {
     addr: Ip,
     family: IpFamily,
     cluster_id: u8,
+    pad: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 20)]
 pub struct TunnelValue {
     addr: Ip,
     family: IpFamily,
     key: u8,
+    #[tuitable(offset = 18)]
     node_id: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 48)]
 pub struct EndpointInfo {
     ifindex: u32,
     pad: u16,
     lxc_id: u16,
+    #[tuitable(offset = 8, hex)]
     flags: u32,
     pad2: u32,
+    #[tuitable(offset = 16)]
     mac: Mac,
     pad3: u16,
+    #[tuitable(offset = 24)]
     node_mac: Mac,
     pad4: u16,
+    #[tuitable(offset = 32)]
     sec_id: u32,
+    pad5: [u32; 3],
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct EdtId {
+    /// 限速的 endpoint 的 id
     id: u64,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 48)]
 pub struct EdtInfo {
+    #[tuitable(unit = "bytes_per_second")]
     bps: u64,
+    #[tuitable(time = "ktime")]
     t_last: u64,
+    #[tuitable(unit = "nanoseconds")]
+    /// C 里和 `pad[4]` 是一个 union
     t_horizon_drop: u64,
+    pad: [u64; 3],
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct RemoteEndpointInfo {
     sec_identity: u32,
     tunnel_endpoint: Ipv4,
+    #[tuitable(offset = 8)]
     node_id: u16,
     key: u8,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct AuthKey {
     local_sec_label: u32,
     remote_sec_label: u32,
+    /// 0 是本节点
     remote_node_id: u16,
+    auth_type: AuthType,
+    pad: u8,
+}
+
+/// 认证方式，和 cilium 的 `policy.AuthType` 对应
+#[repr(transparent)]
+#[derive(Default, Clone, Copy)]
+pub struct AuthType {
     auth_type: u8,
 }
 
+impl AuthType {
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.auth_type {
+            0 => "disabled",
+            1 => "spire",
+            2 => "test-always-fail",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for AuthType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_cell().fmt(f)
+    }
+}
+
+impl ToCell for AuthType {
+    fn to_cell(&self) -> Cell {
+        Cell::Enum {
+            value: self.auth_type as u64,
+            name: self.name(),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct AuthInfo {
+    #[tuitable(time = "unix")]
     expiration: u64,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
+#[tuitable(column(name = "explanation", with = "reason_help", after = "reason"))]
 pub struct MetricsKey {
     reason: MetricsReason,
     dir: MetricsDir,
+    pad: [u16; 3],
+}
+
+fn reason_help(key: &MetricsKey) -> Cell {
+    match key.reason.help() {
+        Some((summary, fix)) => Cell::Help { summary, fix },
+        None => Cell::from(""),
+    }
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 16)]
 pub struct MetricsValue {
+    #[tuitable(unit = "count")]
     count: u64,
+    #[tuitable(unit = "bytes")]
     bytes: u64,
 }
 
+/// LPM key 里的地址，`prefixlen` 是地址的前缀长度
+fn cidr(addr: impl fmt::Display, prefixlen: u32) -> String {
+    format!("{}/{}", addr, prefixlen)
+}
+
+/// LPM key，前 32 位是 saddr，剩下的是 daddr 的前缀
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
+#[tuitable(column(name = "daddr", with = "egress_gw_daddr", after = "daddr"))]
 pub struct EgressGwPolicyKey {
+    #[tuitable(skip)]
+    prefixlen: u32,
     saddr: Ipv4,
+    #[tuitable(skip)]
     daddr: Ipv4,
 }
 
+fn egress_gw_daddr(key: &EgressGwPolicyKey) -> String {
+    cidr(&key.daddr, key.prefixlen.saturating_sub(32))
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct EgressGwPolicyEntry {
     egress_ip: Ipv4,
     gateway_ip: Ipv4,
 }
 
+/// LPM key，前 32 位是 src_ip，剩下的是 dst_cidr 的前缀
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
+#[tuitable(column(name = "dst_cidr", with = "srv6_vrf_dst4", after = "dst_cidr"))]
 pub struct Srv6VrfKey4 {
+    #[tuitable(skip)]
+    prefixlen: u32,
     src_ip: Ipv4,
+    #[tuitable(skip)]
     dst_cidr: Ipv4,
 }
 
+fn srv6_vrf_dst4(key: &Srv6VrfKey4) -> String {
+    cidr(&key.dst_cidr, key.prefixlen.saturating_sub(32))
+}
+
+/// LPM key，前 128 位是 src_ip，剩下的是 dst_cidr 的前缀
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 36)]
+#[tuitable(column(name = "dst_cidr", with = "srv6_vrf_dst6", after = "dst_cidr"))]
 pub struct Srv6VrfKey6 {
+    #[tuitable(skip)]
+    prefixlen: u32,
     src_ip: Ipv6,
+    #[tuitable(skip)]
     dst_cidr: Ipv6,
 }
 
+fn srv6_vrf_dst6(key: &Srv6VrfKey6) -> String {
+    cidr(&key.dst_cidr, key.prefixlen.saturating_sub(128))
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 4)]
+pub struct Srv6VrfValue {
+    vrf_id: u32,
+}
+
+/// LPM key，前 32 位是 vrf_id，剩下的是 dst_cidr 的前缀
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
+#[tuitable(column(name = "dst_cidr", with = "srv6_policy_dst4", after = "dst_cidr"))]
 pub struct Srv6PolicyKey4 {
+    #[tuitable(skip)]
+    prefixlen: u32,
     vrf_id: u32,
+    #[tuitable(skip)]
     dst_cidr: Ipv4,
 }
 
+fn srv6_policy_dst4(key: &Srv6PolicyKey4) -> String {
+    cidr(&key.dst_cidr, key.prefixlen.saturating_sub(32))
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
+#[tuitable(column(name = "dst_cidr", with = "srv6_policy_dst6", after = "dst_cidr"))]
 pub struct Srv6PolicyKey6 {
+    #[tuitable(skip)]
+    prefixlen: u32,
     vrf_id: u32,
+    #[tuitable(skip)]
     dst_cidr: Ipv6,
 }
 
+fn srv6_policy_dst6(key: &Srv6PolicyKey6) -> String {
+    cidr(&key.dst_cidr, key.prefixlen.saturating_sub(32))
+}
+
+/// policy map 的值，封装时使用的 SID
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 16)]
+pub struct Srv6Sid {
+    sid: Ipv6,
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct Srv6Ipv4_2tuple {
     src: Ipv4,
     dst: Ipv4,
@@ -185,6 +332,7 @@ pub struct Srv6Ipv4_2tuple {
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 32)]
 pub struct Srv6Ipv6_2tuple {
     src: Ipv6,
     dst: Ipv6,
@@ -192,45 +340,135 @@ pub struct Srv6Ipv6_2tuple {
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 4)]
 pub struct VtepKey {
     vtep_ip: Ipv4,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 16)]
 pub struct VtepValue {
-    vtep_mac: u64,
-    tunnel_endpoint: u32,
+    /// C 里是 `__u64`，只用了前 6 个字节
+    vtep_mac: Mac,
+    pad: u16,
+    #[tuitable(offset = 8)]
+    tunnel_endpoint: Ipv4,
+    pad2: u32,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 1)]
 pub struct EncryptConfig {
+    /// 当前使用的 IPsec key 的编号，也是 SPI
     encrypt_key: u8,
 }
 
+/// array map 的 key
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 4)]
+pub struct ArrayIndex {
+    index: u32,
+}
+
+bitflags! {
+    /// ct tuple 的方向，没有 IN 时是 TUPLE_F_OUT
+    #[derive(Default)]
+    pub struct TupleFlags: u8 {
+        const IN = 1;
+        const RELATED = 1 << 1;
+        const SERVICE = 1 << 2;
+    }
+}
+
+/// 按 `cilium bpf ct list` 的顺序显示成 `src:sport -> dst:dport (dir)`，
+/// ICMP 没有端口，显示成 `src -> dst (dir, id N)`
+fn ct_tuple(
+    daddr: IpAddr,
+    saddr: IpAddr,
+    dport: u16,
+    sport: u16,
+    nexthdr: &L4Proto,
+    flags: &TupleFlags,
+) -> String {
+    // 方向只影响标记，daddr 总是和 sport 一起，saddr 总是和 dport 一起
+    let mut dir = vec![if flags.contains(TupleFlags::IN) {
+        "in"
+    } else {
+        "out"
+    }];
+    let (src, dst) = (SocketAddr::new(daddr, sport), SocketAddr::new(saddr, dport));
+    if flags.contains(TupleFlags::RELATED) {
+        dir.push("related");
+    }
+    if flags.contains(TupleFlags::SERVICE) {
+        dir.push("service");
+    }
+    if nexthdr.is_icmp() {
+        // echo 的 identifier 放在其中一个端口，另一个是 0
+        let id = format!("id {}", dport | sport);
+        if dport | sport != 0 {
+            dir.push(&id);
+        }
+        return format!("{} -> {} ({})", daddr, saddr, dir.join(", "));
+    }
+    format!("{} -> {} ({})", src, dst, dir.join(", "))
+}
+
+fn ct_tuple4(tuple: &Ipv4CtTuple) -> String {
+    ct_tuple(
+        tuple.daddr.addr().into(),
+        tuple.saddr.addr().into(),
+        tuple.dport.get(),
+        tuple.sport.get(),
+        &tuple.nexthdr,
+        &tuple.flags,
+    )
+}
+
+fn ct_tuple6(tuple: &Ipv6CtTuple) -> String {
+    ct_tuple(
+        tuple.daddr.addr().into(),
+        tuple.saddr.addr().into(),
+        tuple.dport.get(),
+        tuple.sport.get(),
+        &tuple.nexthdr,
+        &tuple.flags,
+    )
+}
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 38)]
+#[tuitable(column(name = "tuple", with = "ct_tuple6"))]
 pub struct Ipv6CtTuple {
     // saddr 和 daddr 是反的
     daddr: Ipv6,
     saddr: Ipv6,
+    #[tuitable(offset = 32)]
     dport: Port,
     sport: Port,
+    #[tuitable(offset = 36)]
     nexthdr: L4Proto,
-    flags: u8,
+    flags: TupleFlags,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 14)]
+#[tuitable(column(name = "tuple", with = "ct_tuple4"))]
 pub struct Ipv4CtTuple {
     // saddr 和 daddr 是反的
     daddr: Ipv4,
     saddr: Ipv4,
+    #[tuitable(offset = 8)]
     dport: Port,
     sport: Port,
+    #[tuitable(offset = 12)]
     nexthdr: L4Proto,
-    flags: u8,
+    flags: TupleFlags,
 }
 
 bitflags! {
@@ -249,12 +487,6 @@ bitflags! {
     }
 }
 
-impl fmt::Display for CtEntryFlags {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
-    }
-}
-
 bitflags! {
     #[derive(Default)]
     pub struct TCPFlags: u8 {
@@ -267,38 +499,46 @@ bitflags! {
     }
 }
 
-impl fmt::Display for TCPFlags {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
-    }
-}
-
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 56)]
 pub struct CtEntry {
+    #[tuitable(unit = "count")]
     rx_packets: u64,
+    #[tuitable(unit = "bytes")]
     rx_bytes: u64,
+    #[tuitable(unit = "count")]
     tx_packets: u64,
+    #[tuitable(unit = "bytes")]
     tx_bytes: u64,
+    #[tuitable(offset = 32, time = "mono")]
     lifetime: u32,
     flags: CtEntryFlags,
+    #[tuitable(offset = 38)]
     rev_nat_index: u16,
     ifindex: u16,
+    #[tuitable(offset = 42)]
     tx_flags_seen: TCPFlags,
     rx_flags_seen: TCPFlags,
+    #[tuitable(offset = 44)]
     src_sec_id: u32,
+    #[tuitable(time = "mono")]
     last_tx_report: u32,
+    #[tuitable(time = "mono")]
     last_rx_report: u32,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
 pub struct Lb6Key {
     address: Ipv6,
     dport: Port,
+    #[tuitable(offset = 18)]
     backend_slot: u16,
     proto: L4Proto,
     scope: u8,
+    pad: [u8; 2],
 }
 
 bitflags! {
@@ -325,55 +565,92 @@ bitflags! {
     }
 }
 
-impl fmt::Display for ServiceFlags {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
-    }
-}
-
-impl fmt::Display for ServiceFlags2 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.0, f)
-    }
-}
-
-#[repr(C)]
-#[derive(Default, TuiTable)]
+/// 显示成 `SYN|ACK` 这样的名字，名字去掉共同的前缀。
+/// 后面可以加 `[NAME = Type::FLAG]`，表示这一位没有设置时显示的名字
+macro_rules! impl_flags {
+    ($($ty:ty => $prefix:literal $([$($unset:literal = $flag:expr),*])?),* $(,)?) => {
+        $(impl ToCell for $ty {
+            fn to_cell(&self) -> Cell {
+                Cell::Flags {
+                    bits: self.bits() as u64,
+                    known: Self::all()
+                        .iter_names()
+                        .map(|(name, flag)| {
+                            (name.strip_prefix($prefix).unwrap_or(name), flag.bits() as u64)
+                        })
+                        .collect(),
+                    unset: vec![$($(($unset, $flag.bits() as u64)),*)?],
+                }
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.to_cell().fmt(f)
+            }
+        })*
+    };
+}
+
+impl_flags!(
+    PolicyEntryFlags => "",
+    CtEntryFlags => "",
+    TCPFlags => "",
+    ServiceFlags => "SVC_FLAG_",
+    ServiceFlags2 => "SVC_FLAG_",
+    TupleFlags => "" ["OUT" = TupleFlags::IN],
+);
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct Lb6Service {
     backend_id: u32,
     count: u16,
     rev_nat_index: u16,
     flags: ServiceFlags,
     flags2: ServiceFlags2,
+    pad: [u8; 2],
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
 pub struct Lb6Backend {
     address: Ipv6,
+    #[tuitable(offset = 16)]
     port: Port,
     proto: L4Proto,
     flags: u8,
-    cluster_id: u8,
+    #[tuitable(offset = 20)]
+    cluster_id: u16,
+    zone: u8,
+    pad: u8,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 20)]
 pub struct Lb6ReverseNat {
     address: Ipv6,
     port: Port,
+    pad: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 32)]
 pub struct Ipv6RevnatTuple {
     cookie: u64,
+    #[tuitable(offset = 8)]
     address: Ipv6,
     port: Port,
+    pad: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 20)]
 pub struct Ipv6RevnatEntry {
     address: Ipv6,
     port: Port,
@@ -382,100 +659,264 @@ pub struct Ipv6RevnatEntry {
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct Lb4Key {
     address: Ipv4,
     dport: Port,
+    #[tuitable(offset = 6)]
     backend_slot: u16,
     proto: L4Proto,
     scope: u8,
+    pad: [u8; 2],
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct Lb4Service {
     backend_id: u32,
     count: u16,
     rev_nat_index: u16,
     flags: ServiceFlags,
     flags2: ServiceFlags2,
+    pad: [u8; 2],
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 4)]
 pub struct Lb4BackendKey {
     backend_id: u32,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
 pub struct Lb4Backend {
     address: Ipv4,
+    #[tuitable(offset = 4)]
     port: Port,
     proto: L4Proto,
     flags: u8,
-    cluster_id: u8,
+    #[tuitable(offset = 8)]
+    cluster_id: u16,
+    zone: u8,
+    pad: u8,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 2)]
 pub struct Lb4ReverseNatKey {
     rev_nat_index: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct Lb4ReverseNat {
     address: Ipv4,
     port: Port,
+    pad: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 16)]
 pub struct Ipv4RevnatTuple {
     cookie: u64,
+    #[tuitable(offset = 8)]
     address: Ipv4,
     port: Port,
+    pad: u16,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 8)]
 pub struct Ipv4RevnatEntry {
     address: Ipv4,
     port: Port,
     rev_nat_index: u16,
 }
 
+/// LPM key，前 32 位是 rev_nat_id 和 pad，剩下的是 addr 的前缀
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 12)]
+#[tuitable(column(name = "addr", with = "lb4_src_range_addr", after = "addr"))]
 pub struct Lb4SrcRangeKey {
+    #[tuitable(skip)]
+    prefixlen: u32,
     rev_nat_id: u16,
     pad: u16,
+    #[tuitable(skip)]
     addr: Ipv4,
 }
 
+fn lb4_src_range_addr(key: &Lb4SrcRangeKey) -> String {
+    cidr(&key.addr, key.prefixlen.saturating_sub(32))
+}
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
+#[tuitable(column(name = "addr", with = "lb6_src_range_addr", after = "addr"))]
+pub struct Lb6SrcRangeKey {
+    #[tuitable(skip)]
+    prefixlen: u32,
+    rev_nat_id: u16,
+    pad: u16,
+    #[tuitable(skip)]
+    addr: Ipv6,
+}
+
+fn lb6_src_range_addr(key: &Lb6SrcRangeKey) -> String {
+    cidr(&key.addr, key.prefixlen.saturating_sub(32))
+}
+
+/// source range map 的值没有用到，只看 key 是否存在
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 1)]
+pub struct SrcRangeValue {
+    pad: u8,
+}
+
 #[repr(C)]
 #[derive(Default, TuiTable)]
 pub struct LpmV4Key {
     addr: Ipv4,
 }
 
+/// v4 和 v6 的 nat entry 共用的部分
 #[repr(C)]
 #[derive(Default, TuiTable)]
-pub struct Ipv4NatEntry {
+#[tuitable(size = 32)]
+pub struct NatEntry {
+    #[tuitable(time = "mono")]
     created: u64,
     host_local: u64,
     pad1: u64,
     pad2: u64,
+}
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 40)]
+pub struct Ipv4NatEntry {
+    #[tuitable(flatten)]
+    common: NatEntry,
+    #[tuitable(offset = 32)]
     to_addr: Ipv4,
     to_port: Port,
 }
 
 #[repr(C)]
 #[derive(Default, TuiTable)]
+#[tuitable(size = 56)]
+pub struct Ipv6NatEntry {
+    #[tuitable(flatten)]
+    common: NatEntry,
+    #[tuitable(offset = 32)]
+    to_addr: Ipv6,
+    to_port: Port,
+}
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 24)]
 pub struct IpcacheKey {
     pad0: u32,
     pad1: u16,
+    #[tuitable(offset = 6)]
     cluster_id: u8,
     family: IpFamily,
+    #[tuitable(offset = 8)]
     addr: Ip,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plain::Plain;
+
+    fn tuple4(
+        daddr: [u8; 4],
+        saddr: [u8; 4],
+        dport: u16,
+        sport: u16,
+        proto: u8,
+        flags: u8,
+    ) -> String {
+        let mut bytes = vec![];
+        bytes.extend(daddr);
+        bytes.extend(saddr);
+        bytes.extend(dport.to_be_bytes());
+        bytes.extend(sport.to_be_bytes());
+        bytes.extend([proto, flags]);
+        let mut tuple = Ipv4CtTuple::default();
+        tuple.copy_from_bytes(&bytes).unwrap();
+        ct_tuple4(&tuple)
+    }
+
+    #[test]
+    fn ct_tuple_in() {
+        assert_eq!(
+            tuple4([10, 0, 0, 1], [10, 0, 0, 2], 80, 40000, 6, 1),
+            "10.0.0.1:40000 -> 10.0.0.2:80 (in)"
+        );
+    }
+
+    #[test]
+    fn ct_tuple_out() {
+        assert_eq!(
+            tuple4([10, 0, 0, 2], [10, 0, 0, 1], 40000, 80, 6, 0),
+            "10.0.0.2:80 -> 10.0.0.1:40000 (out)"
+        );
+        assert_eq!(
+            tuple4([10, 0, 0, 2], [10, 0, 0, 1], 40000, 80, 17, 2 | 4),
+            "10.0.0.2:80 -> 10.0.0.1:40000 (out, related, service)"
+        );
+    }
+
+    #[test]
+    fn ct_tuple_icmp() {
+        assert_eq!(
+            tuple4([10, 0, 0, 1], [10, 0, 0, 2], 0, 7, 1, 0),
+            "10.0.0.1 -> 10.0.0.2 (out, id 7)"
+        );
+        assert_eq!(
+            tuple4([10, 0, 0, 1], [10, 0, 0, 2], 0, 0, 1, 1),
+            "10.0.0.1 -> 10.0.0.2 (in)"
+        );
+    }
+
+    #[test]
+    fn tuple_flags_out() {
+        let flags = |bits| TupleFlags::from_bits_retain(bits).to_string();
+        assert_eq!(flags(0), "OUT");
+        assert_eq!(flags(1), "IN");
+        assert_eq!(flags(2 | 4), "OUT|RELATED|SERVICE");
+        assert_eq!(flags(1 | 2), "IN|RELATED");
+        assert_eq!(
+            TupleFlags::RELATED.to_cell().to_json(),
+            serde_json::json!(["OUT", "RELATED"])
+        );
+    }
+
+    #[test]
+    fn ct_tuple6_in() {
+        let daddr: std::net::Ipv6Addr = "fd00::1".parse().unwrap();
+        let saddr: std::net::Ipv6Addr = "fd00::2".parse().unwrap();
+        let mut bytes = vec![];
+        bytes.extend(daddr.octets());
+        bytes.extend(saddr.octets());
+        bytes.extend(80u16.to_be_bytes());
+        bytes.extend(40000u16.to_be_bytes());
+        bytes.extend([6, 1]);
+        let mut tuple = Ipv6CtTuple::default();
+        tuple.copy_from_bytes(&bytes).unwrap();
+        assert_eq!(ct_tuple6(&tuple), "[fd00::1]:40000 -> [fd00::2]:80 (in)");
+    }
+}
@@ -0,0 +1,11 @@
+/// `#[derive(TuiTable)]` 对错误的属性给出的编译错误
+#[test]
+fn derive_errors() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass.rs");
+    t.compile_fail("tests/ui/unknown_attribute.rs");
+    t.compile_fail("tests/ui/conflicting_format.rs");
+    t.compile_fail("tests/ui/family_not_ip.rs");
+    t.compile_fail("tests/ui/missing_family.rs");
+    t.compile_fail("tests/ui/column_after_missing.rs");
+}
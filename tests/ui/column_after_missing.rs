@@ -0,0 +1,14 @@
+use tuitable::Cell;
+use tuitable_derive::TuiTable;
+
+fn double(entry: &Entry) -> Cell {
+    Cell::Int(entry.id * 2)
+}
+
+#[derive(TuiTable)]
+#[tuitable(column(name = "double", with = "double", after = "ident"))]
+struct Entry {
+    id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use tuitable_derive::TuiTable;
+
+#[derive(TuiTable)]
+struct Entry {
+    #[tuitable(hex, unit = "bytes")]
+    bytes: u64,
+}
+
+fn main() {}
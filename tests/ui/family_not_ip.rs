@@ -0,0 +1,11 @@
+use cilium_map_viewer::IpFamily;
+use tuitable_derive::TuiTable;
+
+#[derive(TuiTable)]
+struct Entry {
+    #[tuitable(family = "version")]
+    port: u16,
+    version: IpFamily,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cilium_map_viewer::Ip;
+use tuitable_derive::TuiTable;
+
+#[derive(TuiTable)]
+struct Entry {
+    address: Ip,
+}
+
+fn main() {}
@@ -0,0 +1,32 @@
+use cilium_map_viewer::{Ip, IpFamily};
+use tuitable::{Cell, TuiTable};
+use tuitable_derive::TuiTable;
+
+fn total(entry: &Entry) -> Cell {
+    Cell::Int(entry.packets + entry.bytes)
+}
+
+#[repr(C)]
+#[derive(Default, TuiTable)]
+#[tuitable(size = 40, column(name = "total", with = "total", after = "bytes"))]
+struct Entry {
+    #[tuitable(rename = "addr")]
+    address: Ip,
+    family: IpFamily,
+    #[tuitable(offset = 17, hex)]
+    flags: u8,
+    pad: [u8; 6],
+    #[tuitable(unit = "count")]
+    packets: u64,
+    #[tuitable(unit = "bytes")]
+    bytes: u64,
+}
+
+fn main() {
+    let entry = Entry::default();
+    assert_eq!(
+        Entry::header(),
+        ["addr", "family", "flags", "packets", "bytes", "total"]
+    );
+    assert!(matches!(entry.cells()[5], Cell::Int(0)));
+}
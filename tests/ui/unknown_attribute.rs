@@ -0,0 +1,9 @@
+use tuitable_derive::TuiTable;
+
+#[derive(TuiTable)]
+struct Entry {
+    #[tuitable(colour = "red")]
+    id: u32,
+}
+
+fn main() {}
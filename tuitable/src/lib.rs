@@ -1,4 +1,473 @@
+use serde::Deserialize;
+pub use serde_json;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
 pub trait TuiTable {
-    fn header() -> Vec<&'static str>;
-    fn row(&self) -> Vec<String>;
+    fn header() -> Vec<String>;
+    fn cells(&self) -> Vec<Cell>;
+
+    fn row(&self) -> Vec<String> {
+        self.cells().iter().map(Cell::to_string).collect()
+    }
+}
+
+/// 一个字段的值，保留原本的类型，`to_string` 得到显示的文本
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    Bool(bool),
+    Int(u64),
+    Hex(u64),
+    /// 带单位的数值，按 `format` 显示
+    Quantity {
+        value: u64,
+        unit: Unit,
+        format: NumberFormat,
+    },
+    /// 一段时间，`unit` 是 `Seconds` 或 `Nanoseconds`，不受 `NumberFormat` 影响
+    Duration {
+        value: u64,
+        unit: Unit,
+    },
+    Ip(IpAddr),
+    Mac([u8; 6]),
+    Port(u16),
+    /// ICMP echo 的 identifier，放在端口的位置，显示成 `id N`，0 显示成 `-`
+    IcmpId(u16),
+    Flags {
+        bits: u64,
+        /// 这个类型所有有名字的位，不管是否设置
+        known: Vec<(&'static str, u64)>,
+        /// 位没有设置时显示的名字，比如 ct tuple 没有 `IN` 时是 `OUT`
+        unset: Vec<(&'static str, u64)>,
+    },
+    Enum {
+        value: u64,
+        name: Option<&'static str>,
+    },
+    Bytes(Vec<u8>),
+    /// map 里原始的时间，需要知道当前的时钟才能换算成 `Time`
+    Timestamp {
+        value: u64,
+        source: TimeSource,
+    },
+    /// 换算好的时间，`at` 和 `now` 都是 unix 纳秒，`value` 是 map 里原始的值
+    Time {
+        value: u64,
+        at: i64,
+        now: i64,
+        /// 显示成 ISO 时间，否则显示相对现在的时间
+        iso: bool,
+    },
+    Text(String),
+    /// 一行说明，详情里再显示怎么处理
+    Help {
+        summary: &'static str,
+        fix: &'static str,
+    },
+}
+
+impl Cell {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Cell::Bool(b) => Value::from(*b),
+            Cell::Int(n)
+            | Cell::Hex(n)
+            | Cell::Quantity { value: n, .. }
+            | Cell::Duration { value: n, .. } => Value::from(*n),
+            Cell::Port(n) | Cell::IcmpId(n) => Value::from(*n),
+            // 时间的格式不随 `iso` 变化，换算不了时 relative（相对现在的秒数）和 iso 是 null
+            Cell::Timestamp { value, .. } => serde_json::json!({
+                "raw": value,
+                "relative": null,
+                "iso": null,
+            }),
+            Cell::Time { value, at, now, .. } => serde_json::json!({
+                "raw": value,
+                "relative": (at - now) / 1_000_000_000,
+                "iso": iso_time(*at),
+            }),
+            Cell::Flags { bits, known, unset } => Value::Array(
+                flag_names(*bits, known, unset)
+                    .into_iter()
+                    .map(Value::from)
+                    .collect(),
+            ),
+            Cell::Enum {
+                name: Some(name), ..
+            } => Value::from(*name),
+            Cell::Enum { value, name: None } => Value::from(*value),
+            _ => Value::from(self.to_string()),
+        }
+    }
+
+    /// 用于排序，同类型的值按原本的类型比较
+    pub fn compare(&self, other: &Cell) -> Ordering {
+        match (self, other) {
+            (Cell::Bool(a), Cell::Bool(b)) => a.cmp(b),
+            (Cell::Int(a), Cell::Int(b)) => a.cmp(b),
+            (Cell::Hex(a), Cell::Hex(b)) => a.cmp(b),
+            (Cell::Quantity { value: a, .. }, Cell::Quantity { value: b, .. }) => a.cmp(b),
+            (Cell::Duration { value: a, .. }, Cell::Duration { value: b, .. }) => a.cmp(b),
+            (Cell::Ip(a), Cell::Ip(b)) => a.cmp(b),
+            (Cell::Mac(a), Cell::Mac(b)) => a.cmp(b),
+            (Cell::Port(a), Cell::Port(b)) => a.cmp(b),
+            (Cell::IcmpId(a), Cell::IcmpId(b)) => a.cmp(b),
+            (Cell::Flags { bits: a, .. }, Cell::Flags { bits: b, .. }) => a.cmp(b),
+            (Cell::Enum { value: a, .. }, Cell::Enum { value: b, .. }) => a.cmp(b),
+            (Cell::Bytes(a), Cell::Bytes(b)) => a.cmp(b),
+            (Cell::Timestamp { value: a, .. }, Cell::Timestamp { value: b, .. }) => a.cmp(b),
+            (Cell::Time { at: a, .. }, Cell::Time { at: b, .. }) => a.cmp(b),
+            _ => self.to_string().cmp(&other.to_string()),
+        }
+    }
+
+    /// 详情里在值下面额外显示的行，flags 展开成每一位
+    pub fn details(&self) -> Vec<String> {
+        match self {
+            Cell::Flags { bits, known, unset } => {
+                let mut lines: Vec<_> = unset
+                    .iter()
+                    .map(|(name, flag)| {
+                        let set = if bits & flag == 0 { 'x' } else { ' ' };
+                        format!("[{}] {} !{:#x}", set, name, flag)
+                    })
+                    .collect();
+                lines.extend(known.iter().map(|(name, flag)| {
+                    let set = if bits & flag == *flag { 'x' } else { ' ' };
+                    format!("[{}] {} {:#x}", set, name, flag)
+                }));
+                let unknown = unknown_bits(*bits, known);
+                if unknown != 0 {
+                    lines.push(format!("[x] {:#x} (unknown)", unknown));
+                }
+                lines
+            }
+            Cell::Help { fix, .. } => vec![fix.to_string()],
+            _ => vec![],
+        }
+    }
+
+    /// 合并 per-CPU 的值，只有计数可以相加
+    pub fn add(&self, other: &Cell) -> Option<Cell> {
+        match (self, other) {
+            (Cell::Int(a), Cell::Int(b)) => Some(Cell::Int(a.wrapping_add(*b))),
+            (
+                Cell::Quantity {
+                    value: a,
+                    unit,
+                    format,
+                },
+                Cell::Quantity { value: b, .. },
+            ) => Some(Cell::Quantity {
+                value: a.wrapping_add(*b),
+                unit: *unit,
+                format: *format,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cell::Bool(b) => b.fmt(f),
+            Cell::Int(n) => n.fmt(f),
+            Cell::Hex(n) => write!(f, "{:#x}", n),
+            Cell::Quantity {
+                value,
+                format: NumberFormat::Raw,
+                ..
+            } => value.fmt(f),
+            Cell::Quantity {
+                value,
+                format: NumberFormat::Grouped,
+                ..
+            } => f.write_str(&grouped(*value)),
+            Cell::Quantity {
+                value,
+                unit,
+                format: NumberFormat::Units,
+            } => unit.format(*value).fmt(f),
+            Cell::Duration { value, unit } => unit.format(*value).fmt(f),
+            Cell::Ip(ip) => ip.fmt(f),
+            Cell::Mac(octets) => {
+                let hex: Vec<_> = octets.iter().map(|n| format!("{:02x}", n)).collect();
+                f.write_str(&hex.join(":"))
+            }
+            Cell::Port(port) => port.fmt(f),
+            Cell::IcmpId(0) => f.write_str("-"),
+            Cell::IcmpId(id) => write!(f, "id {}", id),
+            Cell::Flags { bits, known, unset } => {
+                let names = flag_names(*bits, known, unset);
+                if names.is_empty() {
+                    f.write_str("0")
+                } else {
+                    f.write_str(&names.join("|"))
+                }
+            }
+            Cell::Enum {
+                name: Some(name), ..
+            } => name.fmt(f),
+            // 没有名字的值显示数字
+            Cell::Enum { value, name: None } => value.fmt(f),
+            Cell::Bytes(bytes) => {
+                let hex: Vec<_> = bytes.iter().map(|n| format!("{:02x}", n)).collect();
+                f.write_str(&hex.concat())
+            }
+            Cell::Timestamp { value, .. } => value.fmt(f),
+            Cell::Time { at, iso: true, .. } => f.write_str(&iso_time(*at)),
+            Cell::Time { at, now, .. } if at == now => f.write_str("now"),
+            Cell::Time { at, now, .. } => {
+                let ago = Unit::Nanoseconds.format(at.abs_diff(*now));
+                if at > now {
+                    write!(f, "in {}", ago)
+                } else {
+                    write!(f, "{} ago", ago)
+                }
+            }
+            Cell::Text(text) => text.fmt(f),
+            Cell::Help { summary, .. } => summary.fmt(f),
+        }
+    }
+}
+
+/// 没有设置的位在 `unset` 里的名字和设置了的位的名字，没有名字的位合在一起用十六进制显示
+fn flag_names(
+    bits: u64,
+    known: &[(&'static str, u64)],
+    unset: &[(&'static str, u64)],
+) -> Vec<String> {
+    let mut names: Vec<_> = unset
+        .iter()
+        .filter(|(_, flag)| bits & flag == 0)
+        .chain(known.iter().filter(|(_, flag)| bits & flag == *flag))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let unknown = unknown_bits(bits, known);
+    if unknown != 0 {
+        names.push(format!("{:#x}", unknown));
+    }
+    names
+}
+
+fn unknown_bits(bits: u64, known: &[(&'static str, u64)]) -> u64 {
+    known.iter().fold(bits, |bits, (_, flag)| bits & !flag)
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::Text(text)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::Text(text.to_string())
+    }
+}
+
+/// `Quantity` 的显示方式，排序和导出的 json 都用原始的数值
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberFormat {
+    /// 原始的数值，方便脚本处理
+    #[default]
+    Raw,
+    /// 加千位分隔符，如 `1,234,567`
+    Grouped,
+    /// 带单位，如 `1.2MiB`、`3.4Gbit/s`
+    Units,
+}
+
+impl NumberFormat {
+    /// 依次切换
+    pub fn next(self) -> Self {
+        match self {
+            NumberFormat::Raw => NumberFormat::Grouped,
+            NumberFormat::Grouped => NumberFormat::Units,
+            NumberFormat::Units => NumberFormat::Raw,
+        }
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            NumberFormat::Raw => "raw",
+            NumberFormat::Grouped => "grouped",
+            NumberFormat::Units => "units",
+        })
+    }
+}
+
+impl FromStr for NumberFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(NumberFormat::Raw),
+            "grouped" => Ok(NumberFormat::Grouped),
+            "units" => Ok(NumberFormat::Units),
+            _ => Err(format!(
+                "invalid number format {:?}, expected raw, grouped or units",
+                s
+            )),
+        }
+    }
+}
+
+/// 1,234,567
+fn grouped(value: u64) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    /// 包数之类的计数，用 k、M、G 显示
+    Count,
+    Bytes,
+    Seconds,
+    Nanoseconds,
+    /// 字节每秒，显示成 bit/s
+    BytesPerSecond,
+}
+
+impl Unit {
+    pub fn format(self, value: u64) -> String {
+        match self {
+            Unit::Count => scale(value as f64, 1000.0, &["", "k", "M", "G", "T", "P"]),
+            Unit::Bytes => scale(
+                value as f64,
+                1024.0,
+                &["B", "KiB", "MiB", "GiB", "TiB", "PiB"],
+            ),
+            Unit::BytesPerSecond => scale(
+                value as f64 * 8.0,
+                1000.0,
+                &["bit/s", "kbit/s", "Mbit/s", "Gbit/s", "Tbit/s"],
+            ),
+            Unit::Seconds => duration(value),
+            Unit::Nanoseconds if value < 1_000 => format!("{}ns", value),
+            Unit::Nanoseconds if value < 1_000_000 => format!("{:.1}us", value as f64 / 1e3),
+            Unit::Nanoseconds if value < 1_000_000_000 => format!("{:.1}ms", value as f64 / 1e6),
+            Unit::Nanoseconds => duration(value / 1_000_000_000),
+        }
+    }
+}
+
+fn scale(mut value: f64, base: f64, units: &[&str]) -> String {
+    let mut unit = 0;
+    while value >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", value, units[0])
+    } else {
+        format!("{:.1}{}", value, units[unit])
+    }
+}
+
+/// map 里时间的来源
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeSource {
+    /// `bpf_mono_now()`，ktime 的秒数或者 jiffies
+    Mono,
+    /// `bpf_ktime_get_ns()`
+    Ktime,
+    /// unix 时间的纳秒数，bpf 里的 `utime_get_time()`
+    Unix,
+}
+
+/// UTC 的 `2006-01-02T15:04:05Z`
+fn iso_time(nanos: i64) -> String {
+    let secs = nanos.div_euclid(1_000_000_000);
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // 按公历把天数换算成年月日
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// 1d2h3m4s 这样的时长
+fn duration(secs: u64) -> String {
+    let (d, h, m, s) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if d > 0 {
+        format!("{}d{}h{}m{}s", d, h, m, s)
+    } else if h > 0 {
+        format!("{}h{}m{}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m{}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+pub trait ToCell {
+    fn to_cell(&self) -> Cell;
+}
+
+macro_rules! impl_to_cell {
+    ($($ty:ty),*) => {
+        $(impl ToCell for $ty {
+            fn to_cell(&self) -> Cell {
+                Cell::Int(*self as u64)
+            }
+        })*
+    };
+}
+
+impl_to_cell!(u8, u16, u32, u64);
+
+impl ToCell for bool {
+    fn to_cell(&self) -> Cell {
+        Cell::Bool(*self)
+    }
+}
+
+impl ToCell for Ipv4Addr {
+    fn to_cell(&self) -> Cell {
+        Cell::Ip(IpAddr::V4(*self))
+    }
+}
+
+impl ToCell for Ipv6Addr {
+    fn to_cell(&self) -> Cell {
+        Cell::Ip(IpAddr::V6(*self))
+    }
+}
+
+impl<const N: usize> ToCell for [u8; N] {
+    fn to_cell(&self) -> Cell {
+        Cell::Bytes(self.to_vec())
+    }
 }
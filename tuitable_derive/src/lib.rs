@@ -1,58 +1,334 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{Data, DeriveInput, Fields, Type};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Field, Fields, Ident, LitInt, LitStr, Path, Result, Type};
 
-#[proc_macro_derive(TuiTable)]
+/// 字段上可以写 `#[tuitable(...)]`：
+///
+/// - `skip`：不显示这个字段，名字以 `pad` 开头的字段默认不显示
+/// - `rename = "name"`：表头使用的名字
+/// - `hex`：整数用十六进制显示
+/// - `display_with = "path::to::fn"`：用 `fn(&T) -> impl Into<Cell>` 转换
+/// - `family = "field"`：`Ip` 类型的字段根据哪个 `IpFamily` 字段区分 v4/v6，默认是 `family`
+/// - `unit = "count" | "bytes" | "seconds" | "nanoseconds" | "bytes_per_second"`：带单位显示，
+///   `seconds` 和 `nanoseconds` 是时长，不受数字显示方式的切换影响
+/// - `time = "mono" | "ktime" | "unix"`：时间戳，分别是 `bpf_mono_now()`、`bpf_ktime_get_ns()` 和 `utime_get_time()`（unix 纳秒）
+/// - `flatten` / `flatten = "prefix"`：字段本身实现了 `TuiTable`，展开成它的各列，表头加上前缀
+/// - `offset = N`：编译时检查字段的偏移，和 C 里的 `offsetof` 对应
+///
+/// 结构体上可以写 `#[tuitable(...)]`：
+///
+/// - `size = N`：编译时检查 `size_of`，和 C 里的 `sizeof` 对应
+/// - `column(name = "name", with = "path::to::fn", after = "field")`：加一列，用 `fn(&Self) -> impl Into<Cell>` 计算，
+///   放在 `after` 字段后面，没有 `after` 时放在所有字段前面
+#[proc_macro_derive(TuiTable, attributes(tuitable))]
 pub fn table_derive(input: TokenStream) -> TokenStream {
-    let ast: DeriveInput = syn::parse(input).unwrap();
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
-    let name = ast.ident;
-    let mut fields = vec![];
-    let mut header = vec![];
-    let mut to_string = vec![];
-    if let Data::Struct(data) = ast.data {
-        if let Fields::Named(fields_named) = data.fields {
-            for field in fields_named.named.iter() {
-                if let Some(ident) = field.ident.clone() {
-                    if !ident.to_string().starts_with("pad") {
-                        header.push(ident.to_string());
-                        fields.push((ident, field.ty.clone()));
+    let ast = syn::parse_macro_input!(input as DeriveInput);
+    expand(ast).unwrap_or_else(Error::into_compile_error).into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    skip: bool,
+    rename: Option<LitStr>,
+    hex: bool,
+    display_with: Option<Path>,
+    family: Option<Ident>,
+    unit: Option<Ident>,
+    time: Option<Ident>,
+    flatten: Option<LitStr>,
+    offset: Option<LitInt>,
+}
+
+impl FieldAttrs {
+    fn parse(field: &Field) -> Result<Self> {
+        let mut attrs = Self::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("tuitable")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("hex") {
+                    attrs.hex = true;
+                } else if meta.path.is_ident("display_with") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    attrs.display_with = Some(path.parse()?);
+                } else if meta.path.is_ident("family") {
+                    let family: LitStr = meta.value()?.parse()?;
+                    attrs.family = Some(family.parse()?);
+                } else if meta.path.is_ident("unit") {
+                    let unit: LitStr = meta.value()?.parse()?;
+                    attrs.unit = Some(match unit.value().as_str() {
+                        "count" => format_ident!("Count"),
+                        "bytes" => format_ident!("Bytes"),
+                        "seconds" => format_ident!("Seconds"),
+                        "nanoseconds" => format_ident!("Nanoseconds"),
+                        "bytes_per_second" => format_ident!("BytesPerSecond"),
+                        _ => {
+                            return Err(Error::new_spanned(
+                                unit,
+                                "expected one of \"count\", \"bytes\", \"seconds\", \"nanoseconds\", \"bytes_per_second\"",
+                            ))
+                        }
+                    });
+                } else if meta.path.is_ident("time") {
+                    let time: LitStr = meta.value()?.parse()?;
+                    attrs.time = Some(match time.value().as_str() {
+                        "mono" => format_ident!("Mono"),
+                        "ktime" => format_ident!("Ktime"),
+                        "unix" => format_ident!("Unix"),
+                        _ => {
+                            return Err(Error::new_spanned(
+                                time,
+                                "expected one of \"mono\", \"ktime\", \"unix\"",
+                            ))
+                        }
+                    });
+                } else if meta.path.is_ident("offset") {
+                    attrs.offset = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = Some(if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse()?
+                    } else {
+                        LitStr::new("", meta.path.span())
+                    });
+                } else {
+                    return Err(meta.error("unknown tuitable attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        let formats = [
+            attrs.hex,
+            attrs.display_with.is_some(),
+            attrs.unit.is_some(),
+            attrs.time.is_some(),
+        ];
+        if formats.iter().filter(|f| **f).count() > 1 {
+            return Err(Error::new_spanned(
+                field,
+                "`hex`, `display_with`, `unit` and `time` can not be used together",
+            ));
+        }
+        if attrs.flatten.is_some()
+            && (formats.contains(&true) || attrs.rename.is_some() || attrs.family.is_some())
+        {
+            return Err(Error::new_spanned(
+                field,
+                "`flatten` can not be used with other attributes",
+            ));
+        }
+        Ok(attrs)
+    }
+}
+
+struct Column {
+    name: LitStr,
+    with: Path,
+    after: Option<Ident>,
+}
+
+#[derive(Default)]
+struct StructAttrs {
+    size: Option<LitInt>,
+    /// 计算出来的列
+    columns: Vec<Column>,
+}
+
+impl StructAttrs {
+    fn parse(ast: &DeriveInput) -> Result<Self> {
+        let mut attrs = Self::default();
+        for attr in ast.attrs.iter().filter(|a| a.path().is_ident("tuitable")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("size") {
+                    attrs.size = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("column") {
+                    let (mut name, mut with, mut after) = (None, None, None);
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("name") {
+                            name = Some(meta.value()?.parse::<LitStr>()?);
+                        } else if meta.path.is_ident("with") {
+                            let path: LitStr = meta.value()?.parse()?;
+                            with = Some(path.parse::<Path>()?);
+                        } else if meta.path.is_ident("after") {
+                            let field: LitStr = meta.value()?.parse()?;
+                            after = Some(field.parse::<Ident>()?);
+                        } else {
+                            return Err(meta.error("expected `name`, `with` or `after`"));
+                        }
+                        Ok(())
+                    })?;
+                    match (name, with) {
+                        (Some(name), Some(with)) => {
+                            attrs.columns.push(Column { name, with, after })
+                        }
+                        _ => return Err(meta.error("`column` needs both `name` and `with`")),
                     }
+                } else {
+                    return Err(meta.error("unknown tuitable attribute"));
                 }
+                Ok(())
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+fn is_ip(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("Ip"))
+}
+
+fn expand(ast: DeriveInput) -> Result<TokenStream2> {
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let name = &ast.ident;
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &data.fields,
+                    "TuiTable can only be derived for structs with named fields",
+                ))
             }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &ast.ident,
+                "TuiTable can only be derived for structs",
+            ))
         }
+    };
+    let mut asserts = vec![];
+    let struct_attrs = StructAttrs::parse(&ast)?;
+    if let Some(size) = &struct_attrs.size {
+        let message = format!("size of `{}` is not {}", name, size);
+        asserts.push(quote!(
+            const _: () = assert!(::core::mem::size_of::<#name>() == #size, #message);
+        ));
     }
-    for (ident, ty) in fields {
-        if let Type::Path(path) = ty {
-            if path.path.is_ident("Ip") {
-                to_string.push(quote!(
-                    if self.family.is_ipv4() {
-                        self.#ident.ipv4().to_string()
-                    } else {
-                        self.#ident.ipv6().to_string()
-                    }
-                ));
-                continue;
+    let mut header = vec![];
+    let mut cells = vec![];
+    for column in &struct_attrs.columns {
+        if let Some(after) = &column.after {
+            if !fields.iter().any(|f| f.ident.as_ref() == Some(after)) {
+                return Err(Error::new_spanned(after, format!("no field `{}`", after)));
             }
         }
-        to_string.push(quote!(self.#ident.to_string()));
     }
-    let output = quote!(
+    // 每个字段之前先放 `after` 上一个字段的列
+    let mut previous = None;
+    for field in fields.iter().map(Some).chain([None]) {
+        for column in struct_attrs
+            .columns
+            .iter()
+            .filter(|c| c.after.as_ref() == previous)
+        {
+            let (column, path) = (&column.name, &column.with);
+            header.push(quote!(header.push(#column.to_string())));
+            cells.push(quote!(cells.push(::tuitable::Cell::from(#path(self)))));
+        }
+        let Some(field) = field else {
+            break;
+        };
+        let attrs = FieldAttrs::parse(field)?;
+        let ident = field.ident.as_ref().unwrap();
+        previous = Some(ident);
+        if let Some(offset) = &attrs.offset {
+            let message = format!("offset of `{}.{}` is not {}", name, ident, offset);
+            asserts.push(quote!(
+                const _: () = assert!(::core::mem::offset_of!(#name, #ident) == #offset, #message);
+            ));
+        }
+        if attrs.skip || ident.to_string().starts_with("pad") {
+            continue;
+        }
+        if let Some(prefix) = attrs.flatten {
+            let ty = &field.ty;
+            header.push(quote!(
+                header.extend(
+                    <#ty as ::tuitable::TuiTable>::header()
+                        .into_iter()
+                        .map(|h| format!("{}{}", #prefix, h)),
+                )
+            ));
+            cells.push(quote!(
+                cells.extend(::tuitable::TuiTable::cells(&self.#ident))
+            ));
+            continue;
+        }
+        let column = match attrs.rename {
+            Some(rename) => rename.value(),
+            None => ident.to_string(),
+        };
+        header.push(quote!(header.push(#column.to_string())));
+        if attrs.family.is_some() && !is_ip(&field.ty) {
+            return Err(Error::new_spanned(
+                field,
+                "`family` only applies to `Ip` fields",
+            ));
+        }
+        let cell = if let Some(path) = attrs.display_with {
+            quote!(::tuitable::Cell::from(#path(&self.#ident)))
+        } else if attrs.hex {
+            quote!(::tuitable::Cell::Hex(self.#ident as u64))
+        } else if let Some(source) = attrs.time {
+            quote!(::tuitable::Cell::Timestamp {
+                value: self.#ident as u64,
+                source: ::tuitable::TimeSource::#source,
+            })
+        } else if let Some(unit) = attrs.unit {
+            if unit == "Seconds" || unit == "Nanoseconds" {
+                quote!(::tuitable::Cell::Duration {
+                    value: self.#ident as u64,
+                    unit: ::tuitable::Unit::#unit,
+                })
+            } else {
+                quote!(::tuitable::Cell::Quantity {
+                    value: self.#ident as u64,
+                    unit: ::tuitable::Unit::#unit,
+                    format: ::tuitable::NumberFormat::Raw,
+                })
+            }
+        } else if is_ip(&field.ty) {
+            let family = attrs.family.unwrap_or_else(|| format_ident!("family"));
+            if !fields.iter().any(|f| f.ident.as_ref() == Some(&family)) {
+                return Err(Error::new_spanned(
+                    field,
+                    format!(
+                        "no field `{}` to tell ipv4 from ipv6, use #[tuitable(family = \"...\")]",
+                        family
+                    ),
+                ));
+            }
+            quote!(
+                if self.#family.is_ipv4() {
+                    ::tuitable::ToCell::to_cell(&self.#ident.ipv4())
+                } else {
+                    ::tuitable::ToCell::to_cell(&self.#ident.ipv6())
+                }
+            )
+        } else {
+            quote!(::tuitable::ToCell::to_cell(&self.#ident))
+        };
+        cells.push(quote!(cells.push(#cell)));
+    }
+    Ok(quote!(
         impl #impl_generics ::tuitable::TuiTable for #name #ty_generics #where_clause {
-        // impl #name {
-            fn header() -> Vec<&'static str> {
-                vec![
-                    #(#header),*
-                ]
+            fn header() -> Vec<String> {
+                let mut header = Vec::new();
+                #(#header;)*
+                header
             }
 
-            fn row(&self) -> Vec<String> {
-                vec![
-                    #(#to_string),*
-                ]
+            fn cells(&self) -> Vec<::tuitable::Cell> {
+                let mut cells = Vec::new();
+                #(#cells;)*
+                cells
             }
         }
         unsafe impl ::plain::Plain for #name {}
-    );
-    output.into()
+        #(#asserts)*
+    ))
 }